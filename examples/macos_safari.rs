@@ -142,33 +142,43 @@ fn output_downloads(results: &[SafariDownloads]) -> Result<(), Box<dyn Error>> {
     ])?;
     for result in results {
         for downloads in &result.results {
-            writer
-                .write_record(&[
-                    downloads.source_url.to_owned(),
-                    downloads.download_path.to_owned(),
-                    downloads.sandbox_id.to_owned(),
-                    downloads.download_bytes.to_string(),
-                    downloads.download_id.to_string(),
-                    downloads.download_entry_finish.to_string(),
-                    downloads.path.join("/").to_owned(),
-                    format!("{:?}", downloads.cnid_path),
-                    downloads.creation.to_string(),
-                    downloads.volume_path.to_owned(),
-                    downloads.volume_url.to_owned(),
-                    downloads.volume_uuid.to_owned(),
-                    downloads.volume_name.to_owned(),
-                    downloads.volume_size.to_string(),
-                    downloads.volume_creation.to_string(),
-                    format!("{:?}", downloads.volume_flag),
-                    downloads.volume_root.to_string(),
-                    downloads.username.to_owned(),
-                    downloads.uid.to_string(),
-                    downloads.folder_index.to_string(),
-                    downloads.creation_options.to_string(),
-                    result.user.to_owned(),
-                    result.path.to_owned(),
-                ])
-                .unwrap();
+            // Bookmark data is optional
+            let bookmark_values = match &downloads.bookmark {
+                Some(bookmark) => vec![
+                    bookmark.path.join("/"),
+                    format!("{:?}", bookmark.cnid_path),
+                    bookmark.creation.to_string(),
+                    bookmark.volume_path.to_owned(),
+                    bookmark.volume_url.to_owned(),
+                    bookmark.volume_uuid.to_owned(),
+                    bookmark.volume_name.to_owned(),
+                    bookmark.volume_size.to_string(),
+                    bookmark.volume_creation.to_string(),
+                    format!("{:?}", bookmark.volume_flag),
+                    bookmark.volume_root.to_string(),
+                    bookmark.username.to_owned(),
+                    bookmark.uid.to_string(),
+                    bookmark.folder_index.to_string(),
+                    bookmark.creation_options.to_string(),
+                ],
+                None => vec![String::new(); 15],
+            };
+
+            let mut record = vec![
+                downloads.source_url.to_owned(),
+                downloads.download_path.to_owned(),
+                downloads.sandbox_id.to_owned(),
+                downloads.download_bytes.to_string(),
+                downloads.download_id.to_string(),
+                downloads
+                    .download_entry_finish
+                    .map(|finish| finish.to_string())
+                    .unwrap_or_default(),
+            ];
+            record.extend(bookmark_values);
+            record.push(result.user.to_owned());
+            record.push(result.path.to_owned());
+            writer.write_record(&record).unwrap();
         }
     }
     writer.flush()?;
@@ -0,0 +1,267 @@
+//! Parse the Safari Bookmarks PLIST file
+//!
+//! Bookmarks are returned as a tree of folders and bookmarks, and as a flattened list with the folder path of each bookmark.
+//! Reading List items are only part of the tree, see `reading_list::SafariReadingList`
+
+use log::warn;
+use plist::Dictionary;
+use serde::Serialize;
+
+use crate::{
+    error::SafariError,
+    plist_values::{get_array, get_data, get_dictionary, get_string, open_plist},
+    users::get_user_files,
+};
+
+// Folder containing the Reading List items
+pub(crate) const READING_LIST_TITLE: &str = "com.apple.ReadingList";
+// Folders nested deeper than this are not parsed. Protects against crafted PLIST files
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Serialize)]
+pub struct SafariBookmarks {
+    pub results: Vec<Bookmark>,
+    pub tree: BookmarkNode,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub enum BookmarkType {
+    Folder,   // WebBookmarkTypeList
+    Bookmark, // WebBookmarkTypeLeaf
+    Proxy,    // WebBookmarkTypeProxy, placeholder for the History menu
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BookmarkSync {
+    pub server_id: Option<String>, // iCloud record ID
+    pub data: Option<Vec<u8>>,     // Serialized iCloud record
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BookmarkNode {
+    pub bookmark_type: BookmarkType,
+    pub uuid: String,
+    pub title: String,
+    pub url: Option<String>, // Only bookmarks have a URL
+    pub sync: Option<BookmarkSync>,
+    pub children: Vec<BookmarkNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Bookmark {
+    pub uuid: String,
+    pub title: String,
+    pub url: String,
+    pub folder_path: String, // Parent folder titles separated by "/" (ex: BookmarksBar/News)
+    pub sync_server_id: Option<String>,
+}
+
+impl SafariBookmarks {
+    /// Get Safari Bookmarks PLIST file for all users
+    pub fn get_users_bookmarks() -> Result<Vec<SafariBookmarks>, SafariError> {
+        let user_files = get_user_files("/Library/Safari/Bookmarks.plist")?;
+
+        let mut safari_bookmarks: Vec<SafariBookmarks> = Vec::new();
+        for user_file in user_files {
+            let tree = SafariBookmarks::get_bookmark_tree(&user_file.path)?;
+            let bookmarks = SafariBookmarks {
+                results: SafariBookmarks::flatten_bookmarks(&tree),
+                tree,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_bookmarks.push(bookmarks);
+        }
+        Ok(safari_bookmarks)
+    }
+
+    /// Parse Safari Bookmarks PLIST file at provided path into a flattened list of bookmarks
+    pub fn get_bookmarks(path: &str) -> Result<Vec<Bookmark>, SafariError> {
+        let tree = SafariBookmarks::get_bookmark_tree(path)?;
+        Ok(SafariBookmarks::flatten_bookmarks(&tree))
+    }
+
+    /// Parse Safari Bookmarks PLIST file at provided path into a tree. The root node is an untitled folder
+    pub fn get_bookmark_tree(path: &str) -> Result<BookmarkNode, SafariError> {
+        let bookmarks = open_plist(path)?;
+        Ok(SafariBookmarks::get_node(&bookmarks, 0))
+    }
+
+    /// Get all bookmarks in a tree with the path of their parent folders. Reading List items are skipped
+    pub fn flatten_bookmarks(tree: &BookmarkNode) -> Vec<Bookmark> {
+        let mut bookmarks: Vec<Bookmark> = Vec::new();
+        SafariBookmarks::flatten_node(tree, &[], &mut bookmarks);
+        bookmarks
+    }
+
+    fn flatten_node(node: &BookmarkNode, folders: &[&str], bookmarks: &mut Vec<Bookmark>) {
+        if node.bookmark_type == BookmarkType::Bookmark {
+            let bookmark = Bookmark {
+                uuid: node.uuid.clone(),
+                title: node.title.clone(),
+                url: node.url.clone().unwrap_or_default(),
+                folder_path: folders.join("/"),
+                sync_server_id: node.sync.as_ref().and_then(|sync| sync.server_id.clone()),
+            };
+            bookmarks.push(bookmark);
+            return;
+        }
+
+        if folders.is_empty() && node.title == READING_LIST_TITLE {
+            return;
+        }
+
+        // The root folder has no title
+        let mut child_folders = folders.to_vec();
+        if !node.title.is_empty() {
+            child_folders.push(&node.title);
+        }
+        for child in &node.children {
+            SafariBookmarks::flatten_node(child, &child_folders, bookmarks);
+        }
+    }
+
+    // Parse a bookmark dictionary and all of its children, up to `MAX_DEPTH` folders deep
+    fn get_node(dict: &Dictionary, depth: usize) -> BookmarkNode {
+        let bookmark_type = match get_string(dict, "WebBookmarkType").as_deref() {
+            Some("WebBookmarkTypeList") => BookmarkType::Folder,
+            Some("WebBookmarkTypeLeaf") => BookmarkType::Bookmark,
+            Some("WebBookmarkTypeProxy") => BookmarkType::Proxy,
+            bookmark_type => {
+                warn!("Unknown Safari bookmark type: {:?}", bookmark_type);
+                BookmarkType::Unknown
+            }
+        };
+
+        // Bookmark titles are stored in the URI dictionary
+        let title = match bookmark_type {
+            BookmarkType::Bookmark => {
+                get_dictionary(dict, "URIDictionary").and_then(|uri| get_string(uri, "title"))
+            }
+            _ => get_string(dict, "Title"),
+        };
+
+        let sync = get_dictionary(dict, "Sync").map(|sync| BookmarkSync {
+            server_id: get_string(sync, "ServerID"),
+            data: get_data(sync, "Data"),
+        });
+
+        let mut children: Vec<BookmarkNode> = Vec::new();
+        let child_values = get_array(dict, "Children");
+        if child_values.is_some() && depth >= MAX_DEPTH {
+            warn!(
+                "Safari bookmarks nested deeper than {} folders, skipping children",
+                MAX_DEPTH
+            );
+        } else if let Some(child_values) = child_values {
+            for child in child_values {
+                match child.as_dictionary() {
+                    Some(child_dict) => {
+                        children.push(SafariBookmarks::get_node(child_dict, depth + 1))
+                    }
+                    None => warn!("Safari bookmark child is not a dictionary"),
+                }
+            }
+        }
+
+        BookmarkNode {
+            bookmark_type,
+            uuid: get_string(dict, "WebBookmarkUUID").unwrap_or_default(),
+            title: title.unwrap_or_default(),
+            url: get_string(dict, "URLString"),
+            sync,
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use plist::{Dictionary, Value};
+
+    use super::{BookmarkType, SafariBookmarks, MAX_DEPTH};
+
+    #[test]
+    fn test_get_bookmark_tree() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Bookmarks.plist");
+        let tree =
+            SafariBookmarks::get_bookmark_tree(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(tree.bookmark_type, BookmarkType::Folder);
+        assert_eq!(tree.uuid, "6A5A9C3D-1B51-4A6E-9E6B-2B8E2F4C1D01");
+        assert_eq!(tree.title, "");
+        assert_eq!(tree.children.len(), 4);
+        assert_eq!(tree.children[0].bookmark_type, BookmarkType::Proxy);
+        assert_eq!(tree.children[0].title, "History");
+
+        let bookmarks_bar = &tree.children[1];
+        assert_eq!(bookmarks_bar.title, "BookmarksBar");
+        assert_eq!(
+            bookmarks_bar.sync.as_ref().unwrap().server_id.as_deref(),
+            Some("BookmarksBarFolder")
+        );
+        assert_eq!(bookmarks_bar.children[0].title, "Apple");
+        assert_eq!(
+            bookmarks_bar.children[0].url.as_deref(),
+            Some("https://www.apple.com/")
+        );
+        assert_eq!(bookmarks_bar.children[1].children.len(), 2);
+    }
+
+    #[test]
+    fn test_get_bookmarks() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Bookmarks.plist");
+        let results = SafariBookmarks::get_bookmarks(&test_location.display().to_string()).unwrap();
+
+        // Reading List items are not bookmarks
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].title, "Apple");
+        assert_eq!(results[0].folder_path, "BookmarksBar");
+        assert_eq!(results[0].sync_server_id.as_deref(), Some("FirstBookmark"));
+
+        assert_eq!(results[2].url, "https://github.com/puffyCid/browser-safari");
+        assert_eq!(results[2].folder_path, "BookmarksBar/News/Tech");
+        assert_eq!(results[2].sync_server_id, None);
+
+        assert_eq!(results[3].folder_path, "BookmarksMenu");
+        assert!(results
+            .iter()
+            .all(|bookmark| !bookmark.folder_path.starts_with("com.apple.ReadingList")));
+    }
+
+    #[test]
+    fn test_get_node_max_depth() {
+        let mut dict = Dictionary::new();
+        dict.insert(
+            String::from("WebBookmarkType"),
+            Value::String(String::from("WebBookmarkTypeList")),
+        );
+        for _ in 0..MAX_DEPTH * 2 {
+            let mut parent = Dictionary::new();
+            parent.insert(
+                String::from("WebBookmarkType"),
+                Value::String(String::from("WebBookmarkTypeList")),
+            );
+            parent.insert(
+                String::from("Children"),
+                Value::Array(vec![Value::Dictionary(dict)]),
+            );
+            dict = parent;
+        }
+
+        let mut node = SafariBookmarks::get_node(&dict, 0);
+        let mut depth = 0;
+        while let Some(child) = node.children.pop() {
+            node = child;
+            depth += 1;
+        }
+        assert_eq!(depth, MAX_DEPTH);
+    }
+}
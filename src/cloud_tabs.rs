@@ -0,0 +1,237 @@
+//! Parse the Safari CloudTabs SQLITE file
+//!
+//! Contains the tabs open on the user's other devices (iPhone, iPad, other Macs) synced through iCloud
+
+use log::{error, warn};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    error::SafariError, sqlite::open_sqlite, time::cocoa_to_unixepoch, users::get_user_files,
+};
+
+const DEVICES_QUERY: &str =
+    "SELECT device_uuid, device_name, last_modified FROM cloud_tab_devices ORDER BY device_name";
+const TABS_QUERY: &str =
+    "SELECT tab_uuid, device_uuid, position, title, url, is_pinned, is_showing_reader FROM cloud_tabs ORDER BY device_uuid, rowid";
+
+#[derive(Debug, Serialize)]
+pub struct SafariCloudTabs {
+    pub results: Vec<CloudTabDevice>,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CloudTabDevice {
+    pub device_uuid: String,
+    pub device_name: Option<String>,
+    pub last_modified: Option<f64>, // UNIX Epoch seconds
+    pub tabs: Vec<CloudTab>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CloudTab {
+    pub tab_uuid: String,
+    pub url: String,
+    pub title: Option<String>,
+    pub position: Option<i64>, // Sort value of the tab on the device
+    pub is_pinned: bool,
+    pub is_showing_reader: bool,
+}
+
+impl SafariCloudTabs {
+    /// Get Safari CloudTabs SQLITE file for all users. Checks both the Safari container and the older `~/Library/Safari` location
+    pub fn get_users_cloud_tabs() -> Result<Vec<SafariCloudTabs>, SafariError> {
+        let mut user_files = get_user_files(
+            "/Library/Containers/com.apple.Safari/Data/Library/Safari/CloudTabs.db",
+        )?;
+        user_files.append(&mut get_user_files("/Library/Safari/CloudTabs.db")?);
+
+        let mut safari_cloud_tabs: Vec<SafariCloudTabs> = Vec::new();
+        for user_file in user_files {
+            let results = SafariCloudTabs::get_cloud_tabs(&user_file.path)?;
+            let cloud_tabs = SafariCloudTabs {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_cloud_tabs.push(cloud_tabs);
+        }
+        Ok(safari_cloud_tabs)
+    }
+
+    /// Query the devices and their tabs based on provided path
+    pub fn get_cloud_tabs(path: &str) -> Result<Vec<CloudTabDevice>, SafariError> {
+        let conn = open_sqlite(path)?;
+
+        let mut devices = SafariCloudTabs::query_devices(&conn)?;
+        let tabs = SafariCloudTabs::query_tabs(&conn)?;
+        for (device_uuid, tab) in tabs {
+            match devices
+                .iter_mut()
+                .find(|device| device.device_uuid == device_uuid)
+            {
+                Some(device) => device.tabs.push(tab),
+                None => warn!(
+                    "No Safari cloud device {} for tab {}",
+                    device_uuid, tab.tab_uuid
+                ),
+            }
+        }
+
+        // Tabs without a position are kept last, in table order
+        for device in devices.iter_mut() {
+            device
+                .tabs
+                .sort_by_key(|tab| (tab.position.is_none(), tab.position));
+        }
+        Ok(devices)
+    }
+
+    // Query the devices syncing tabs
+    fn query_devices(conn: &Connection) -> Result<Vec<CloudTabDevice>, SafariError> {
+        let statement = conn.prepare(DEVICES_QUERY);
+        let mut stmt = match statement {
+            Ok(query) => query,
+            Err(err) => {
+                error!("Failed to compose Safari cloud devices SQL query {:?}", err);
+                return Err(SafariError::BadSQL);
+            }
+        };
+
+        let devices_data = stmt.query_map([], |row| {
+            let last_modified: Option<f64> = row.get("last_modified")?;
+            Ok(CloudTabDevice {
+                device_uuid: row.get("device_uuid")?,
+                device_name: row.get("device_name")?,
+                last_modified: last_modified.map(cocoa_to_unixepoch),
+                tabs: Vec::new(),
+            })
+        });
+
+        match devices_data {
+            Ok(devices_iter) => {
+                let mut devices: Vec<CloudTabDevice> = Vec::new();
+                for device in devices_iter {
+                    match device {
+                        Ok(device_data) => devices.push(device_data),
+                        Err(err) => {
+                            warn!("Failed to iterate through Safari cloud devices: {:?}", err);
+                        }
+                    }
+                }
+                Ok(devices)
+            }
+            Err(err) => {
+                error!(
+                    "Failed to get Safari cloud devices from SQLITE file: {:?}",
+                    err
+                );
+                Err(SafariError::SqliteParse)
+            }
+        }
+    }
+
+    // Query the tabs and the device UUID they belong to
+    fn query_tabs(conn: &Connection) -> Result<Vec<(String, CloudTab)>, SafariError> {
+        let statement = conn.prepare(TABS_QUERY);
+        let mut stmt = match statement {
+            Ok(query) => query,
+            Err(err) => {
+                error!("Failed to compose Safari cloud tabs SQL query {:?}", err);
+                return Err(SafariError::BadSQL);
+            }
+        };
+
+        let tabs_data = stmt.query_map([], |row| {
+            let position: Option<Vec<u8>> = row.get("position")?;
+            let tab = CloudTab {
+                tab_uuid: row.get("tab_uuid")?,
+                url: row.get("url")?,
+                title: row.get("title")?,
+                position: position.and_then(|value| SafariCloudTabs::get_position(&value)),
+                is_pinned: row.get("is_pinned")?,
+                is_showing_reader: row.get("is_showing_reader")?,
+            };
+            Ok((row.get("device_uuid")?, tab))
+        });
+
+        match tabs_data {
+            Ok(tabs_iter) => {
+                let mut tabs: Vec<(String, CloudTab)> = Vec::new();
+                for tab in tabs_iter {
+                    match tab {
+                        Ok(tab_data) => tabs.push(tab_data),
+                        Err(err) => {
+                            warn!("Failed to iterate through Safari cloud tabs: {:?}", err);
+                        }
+                    }
+                }
+                Ok(tabs)
+            }
+            Err(err) => {
+                error!(
+                    "Failed to get Safari cloud tabs from SQLITE file: {:?}",
+                    err
+                );
+                Err(SafariError::SqliteParse)
+            }
+        }
+    }
+
+    // The position is a JSON blob with the sort value of the tab. Ex: {"sortValues":[{"sortValue":1}]}
+    fn get_position(data: &[u8]) -> Option<i64> {
+        let position: Value = match serde_json::from_slice(data) {
+            Ok(results) => results,
+            Err(err) => {
+                warn!("Failed to parse Safari cloud tab position: {:?}", err);
+                return None;
+            }
+        };
+        position["sortValues"][0]["sortValue"].as_i64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::SafariCloudTabs;
+
+    #[test]
+    fn test_get_cloud_tabs() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/CloudTabs.db");
+        let results =
+            SafariCloudTabs::get_cloud_tabs(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].device_name.as_deref(), Some("puffycid iPad"));
+        assert_eq!(results[0].last_modified, Some(1656260000.0));
+        assert_eq!(results[0].tabs.len(), 1);
+        assert!(results[0].tabs[0].is_showing_reader);
+
+        let iphone = &results[1];
+        assert_eq!(iphone.device_uuid, "D1A1B1C1-0000-4000-8000-000000000001");
+        assert_eq!(iphone.tabs.len(), 2);
+        // Sorted by position, not by table order
+        assert_eq!(iphone.tabs[0].position, Some(0));
+        assert!(iphone.tabs[0].is_pinned);
+        assert_eq!(iphone.tabs[1].url, "https://www.cnn.com/");
+        assert_eq!(iphone.tabs[1].title.as_deref(), Some("CNN"));
+        assert_eq!(iphone.tabs[1].position, Some(1));
+        assert!(!iphone.tabs[1].is_pinned);
+    }
+
+    #[test]
+    fn test_get_position() {
+        assert_eq!(
+            SafariCloudTabs::get_position(br#"{"sortValues":[{"sortValue":3}]}"#),
+            Some(3)
+        );
+        assert_eq!(SafariCloudTabs::get_position(br#"{"sortValues":[]}"#), None);
+        assert_eq!(SafariCloudTabs::get_position(b"not json"), None);
+    }
+}
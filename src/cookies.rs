@@ -0,0 +1,235 @@
+//! Parse the Safari Cookies.binarycookies file
+//!
+//! Apple binary cookie format: a `cook` signature, the number of pages and the page sizes (big endian), followed by the pages.
+//! Page contents and cookie records are little endian. Cookie dates are Cocoa timestamps
+
+use std::fs::read;
+
+use log::{error, warn};
+use serde::Serialize;
+
+use crate::{error::SafariError, time::cocoa_to_unixepoch, users::get_user_files};
+
+// Cookie flags
+const SECURE_FLAG: u32 = 0x1;
+const HTTP_ONLY_FLAG: u32 = 0x4;
+
+// Size of a cookie record before its strings
+const COOKIE_HEADER_SIZE: usize = 56;
+
+#[derive(Debug, Serialize)]
+pub struct SafariCookies {
+    pub results: Vec<Cookie>,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Cookie {
+    pub domain: String,
+    pub name: String,
+    pub path: String,
+    pub value: String,
+    pub flags: u32,
+    pub is_secure: bool,
+    pub is_http_only: bool,
+    pub created: f64, // UNIX Epoch seconds
+    pub expires: f64, // UNIX Epoch seconds
+}
+
+impl SafariCookies {
+    /// Get Safari Cookies.binarycookies file for all users. Checks both `~/Library/Cookies` and the Safari container
+    pub fn get_users_cookies() -> Result<Vec<SafariCookies>, SafariError> {
+        let mut user_files = get_user_files("/Library/Cookies/Cookies.binarycookies")?;
+        user_files.append(&mut get_user_files(
+            "/Library/Containers/com.apple.Safari/Data/Library/Cookies/Cookies.binarycookies",
+        )?);
+
+        let mut safari_cookies: Vec<SafariCookies> = Vec::new();
+        for user_file in user_files {
+            let results = SafariCookies::get_cookies(&user_file.path)?;
+            let cookies = SafariCookies {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_cookies.push(cookies);
+        }
+        Ok(safari_cookies)
+    }
+
+    /// Parse Safari Cookies.binarycookies file at provided path
+    pub fn get_cookies(path: &str) -> Result<Vec<Cookie>, SafariError> {
+        let data = match read(path) {
+            Ok(results) => results,
+            Err(err) => {
+                error!("Failed to read cookies file {}: {:?}", path, err);
+                return Err(SafariError::Cookies);
+            }
+        };
+        SafariCookies::parse_cookies(&data)
+    }
+
+    // Parse the file header and every page
+    fn parse_cookies(data: &[u8]) -> Result<Vec<Cookie>, SafariError> {
+        if data.get(0..4) != Some(&b"cook"[..]) {
+            error!("Cookies file does not have a cook signature");
+            return Err(SafariError::Cookies);
+        }
+        let page_count = match get_u32_be(data, 4) {
+            Some(results) => results as usize,
+            None => {
+                error!("Cookies file is too small to contain the page count");
+                return Err(SafariError::Cookies);
+            }
+        };
+
+        let mut page_sizes: Vec<usize> = Vec::new();
+        for page in 0..page_count {
+            match get_u32_be(data, 8 + page * 4) {
+                Some(size) => page_sizes.push(size as usize),
+                None => {
+                    error!("Cookies file is too small to contain the page sizes");
+                    return Err(SafariError::Cookies);
+                }
+            }
+        }
+
+        let mut cookies: Vec<Cookie> = Vec::new();
+        let mut offset = 8 + page_count * 4;
+        for size in page_sizes {
+            match data.get(offset..offset + size) {
+                Some(page) => cookies.append(&mut SafariCookies::parse_page(page)),
+                None => {
+                    warn!("Cookies page at offset {} is truncated", offset);
+                    break;
+                }
+            }
+            offset += size;
+        }
+        Ok(cookies)
+    }
+
+    // Parse the cookie records in a page
+    fn parse_page(page: &[u8]) -> Vec<Cookie> {
+        let mut cookies: Vec<Cookie> = Vec::new();
+        let cookie_count = match get_u32_le(page, 4) {
+            Some(results) => results as usize,
+            None => {
+                warn!("Cookies page is too small to contain the cookie count");
+                return cookies;
+            }
+        };
+
+        for cookie in 0..cookie_count {
+            let cookie_offset = match get_u32_le(page, 8 + cookie * 4) {
+                Some(results) => results as usize,
+                None => {
+                    warn!("Cookies page is too small to contain the cookie offsets");
+                    break;
+                }
+            };
+            match page
+                .get(cookie_offset..)
+                .and_then(SafariCookies::parse_cookie)
+            {
+                Some(results) => cookies.push(results),
+                None => warn!("Failed to parse cookie at page offset {}", cookie_offset),
+            }
+        }
+        cookies
+    }
+
+    // Parse a cookie record
+    fn parse_cookie(record: &[u8]) -> Option<Cookie> {
+        let size = get_u32_le(record, 0)? as usize;
+        if size < COOKIE_HEADER_SIZE {
+            return None;
+        }
+        let record = record.get(..size)?;
+        let flags = get_u32_le(record, 8)?;
+
+        Some(Cookie {
+            domain: get_cstring(record, get_u32_le(record, 16)? as usize)?,
+            name: get_cstring(record, get_u32_le(record, 20)? as usize)?,
+            path: get_cstring(record, get_u32_le(record, 24)? as usize)?,
+            value: get_cstring(record, get_u32_le(record, 28)? as usize)?,
+            flags,
+            is_secure: flags & SECURE_FLAG == SECURE_FLAG,
+            is_http_only: flags & HTTP_ONLY_FLAG == HTTP_ONLY_FLAG,
+            expires: cocoa_to_unixepoch(get_f64_le(record, 40)?),
+            created: cocoa_to_unixepoch(get_f64_le(record, 48)?),
+        })
+    }
+}
+
+// Read a big endian u32 at offset
+fn get_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+// Read a little endian u32 at offset
+fn get_u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+// Read a little endian f64 at offset
+fn get_f64_le(data: &[u8], offset: usize) -> Option<f64> {
+    Some(f64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+// Read a NULL terminated string at offset
+fn get_cstring(data: &[u8], offset: usize) -> Option<String> {
+    let string_data = data.get(offset..)?;
+    let end = string_data.iter().position(|byte| *byte == 0)?;
+    Some(String::from_utf8_lossy(&string_data[..end]).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::SafariCookies;
+
+    #[test]
+    fn test_get_cookies() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Cookies.binarycookies");
+        let results = SafariCookies::get_cookies(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].domain, ".github.com");
+        assert_eq!(results[0].name, "_octo");
+        assert_eq!(results[0].path, "/");
+        assert_eq!(results[0].value, "GH1.1.123456789.1656266400");
+        assert!(results[0].is_secure);
+        assert!(!results[0].is_http_only);
+        assert_eq!(results[0].created, 1656266400.0);
+        assert_eq!(results[0].expires, 1687802400.0);
+
+        assert_eq!(results[1].name, "user_session");
+        assert_eq!(results[1].flags, 5);
+        assert!(results[1].is_secure);
+        assert!(results[1].is_http_only);
+
+        // Second page
+        assert_eq!(results[2].domain, ".cnn.com");
+        assert!(!results[2].is_secure);
+        assert_eq!(results[2].expires, 1656352800.0);
+    }
+
+    #[test]
+    fn test_parse_cookies_truncated() {
+        // Header claims one page that is not in the file
+        let data = [b'c', b'o', b'o', b'k', 0, 0, 0, 1, 0, 0, 0, 64];
+        assert!(SafariCookies::parse_cookies(&data).unwrap().is_empty());
+        assert!(SafariCookies::parse_cookies(b"cook").is_err());
+        assert!(SafariCookies::parse_cookies(&[0, 1, 2, 3]).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! Correlate Safari downloads with the history visits that likely triggered them
+//!
+//! Downloads and History should come from the same user. Each download is linked to visits that
+//! happened before the download started, scored by how close in time the visit was and whether the
+//! visit (or its redirect chain) matches the download URL
+
+use std::collections::HashMap;
+
+use log::warn;
+use serde::Serialize;
+
+use crate::{downloads::Downloads, history::History, time::cocoa_to_unixepoch, url::get_host};
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub enum CorrelationReason {
+    ExactUrl,      // Visit URL is the download URL
+    MatchingHost,  // Visit host is the download host
+    RedirectChain, // A visit in the redirect chain has the download host
+    ClosestVisit,  // Closest visit before the download started
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CorrelatedVisit {
+    pub history_id: i64,
+    pub visit_id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    pub visit_time: f64,     // UNIX Epoch seconds
+    pub seconds_before: f64, // Seconds between the visit and the download start
+    pub confidence: f64,     // Between 0 and 1
+    pub reason: CorrelationReason,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DownloadCorrelation {
+    pub download_id: String,
+    pub source_url: String,
+    pub download_time: Option<f64>,   // UNIX Epoch seconds
+    pub visits: Vec<CorrelatedVisit>, // Sorted by highest confidence
+}
+
+/// Link each download to the visits preceding it within `window_seconds`. Returns nothing if the window is not positive
+pub fn correlate_downloads(
+    downloads: &[Downloads],
+    history: &[History],
+    window_seconds: f64,
+) -> Vec<DownloadCorrelation> {
+    // Confidence is scaled by the window size
+    if window_seconds.is_nan() || window_seconds <= 0.0 {
+        warn!(
+            "Download correlation window must be positive, got {}",
+            window_seconds
+        );
+        return Vec::new();
+    }
+
+    let visits: HashMap<i64, &History> = history
+        .iter()
+        .map(|history| (history.visit_id, history))
+        .collect();
+
+    let mut correlations: Vec<DownloadCorrelation> = Vec::new();
+    for download in downloads {
+        let mut correlation = DownloadCorrelation {
+            download_id: download.download_id.clone(),
+            source_url: download.source_url.clone(),
+            download_time: download.download_entry_date,
+            visits: Vec::new(),
+        };
+
+        // Downloads without a start time cannot be placed relative to history
+        let download_time = match download.download_entry_date {
+            Some(results) => results,
+            None => {
+                correlations.push(correlation);
+                continue;
+            }
+        };
+        let download_host = get_host(&download.source_url);
+
+        let mut closest_visit: Option<CorrelatedVisit> = None;
+        for visit in history {
+            let visit_time = cocoa_to_unixepoch(visit.visit_time);
+            let seconds_before = download_time - visit_time;
+            if seconds_before < 0.0 || seconds_before > window_seconds {
+                continue;
+            }
+            // Closer visits are more likely to be the page the download started from
+            let time_score = 1.0 - seconds_before / window_seconds;
+
+            let (reason, confidence) = if visit.url == download.source_url {
+                (CorrelationReason::ExactUrl, 1.0)
+            } else if download_host.is_some() && get_host(&visit.url) == download_host {
+                (CorrelationReason::MatchingHost, 0.5 + 0.4 * time_score)
+            } else if download_host.is_some()
+                && get_redirect_hosts(visit, &visits).contains(&download_host)
+            {
+                (CorrelationReason::RedirectChain, 0.4 + 0.4 * time_score)
+            } else {
+                (CorrelationReason::ClosestVisit, 0.4 * time_score)
+            };
+
+            let correlated_visit = CorrelatedVisit {
+                history_id: visit.id,
+                visit_id: visit.visit_id,
+                url: visit.url.clone(),
+                title: visit.title.clone(),
+                visit_time,
+                seconds_before,
+                confidence,
+                reason,
+            };
+
+            if correlated_visit.reason != CorrelationReason::ClosestVisit {
+                correlation.visits.push(correlated_visit);
+                continue;
+            }
+            let is_closer = match &closest_visit {
+                Some(closest) => seconds_before < closest.seconds_before,
+                None => true,
+            };
+            if is_closer {
+                closest_visit = Some(correlated_visit);
+            }
+        }
+
+        // Only keep the closest visit if nothing better matched it
+        if let Some(closest) = closest_visit {
+            let already_linked = correlation
+                .visits
+                .iter()
+                .any(|visit| visit.seconds_before <= closest.seconds_before);
+            if !already_linked {
+                correlation.visits.push(closest);
+            }
+        }
+
+        correlation
+            .visits
+            .sort_by(|first, second| second.confidence.total_cmp(&first.confidence));
+        correlations.push(correlation);
+    }
+    correlations
+}
+
+// Get the hosts of all visits in the redirect chain of a visit
+fn get_redirect_hosts(visit: &History, visits: &HashMap<i64, &History>) -> Vec<Option<String>> {
+    let mut hosts: Vec<Option<String>> = Vec::new();
+
+    let mut source = visit.redirect_source;
+    while let Some(visit_id) = source {
+        match visits.get(&visit_id) {
+            // Guard against redirect loops
+            Some(redirect) if hosts.len() < visits.len() => {
+                hosts.push(get_host(&redirect.url));
+                source = redirect.redirect_source;
+            }
+            _ => break,
+        }
+    }
+
+    let mut destination = visit.redirect_destination;
+    while let Some(visit_id) = destination {
+        match visits.get(&visit_id) {
+            Some(redirect) if hosts.len() < visits.len() => {
+                hosts.push(get_host(&redirect.url));
+                destination = redirect.redirect_destination;
+            }
+            _ => break,
+        }
+    }
+    hosts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{correlate_downloads, CorrelationReason};
+    use crate::{downloads::SafariDownloads, history::SafariHistory};
+
+    #[test]
+    fn test_correlate_downloads() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/History.db");
+        let history = SafariHistory::get_history(&test_location.display().to_string()).unwrap();
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+
+        let results = correlate_downloads(&downloads, &history, 3600.0);
+        assert_eq!(results.len(), 3);
+
+        assert_eq!(
+            results[2].download_id,
+            "FA11CA6A-3A6D-46AF-89EA-5BF3ECDB8907"
+        );
+        assert_eq!(results[2].visits.len(), 1);
+        assert_eq!(results[2].visits[0].visit_id, 284);
+        assert_eq!(results[2].visits[0].reason, CorrelationReason::ClosestVisit);
+        assert!(results[2].visits[0].seconds_before < 3.0);
+        assert!(results[2].visits[0].confidence > 0.39);
+    }
+
+    #[test]
+    fn test_correlate_downloads_host() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/History.db");
+        let mut history = SafariHistory::get_history(&test_location.display().to_string()).unwrap();
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+
+        // Point the Google search redirect chain (279 -> 280 -> 281) at the download host
+        for visit in history.iter_mut() {
+            if visit.visit_id == 279 {
+                visit.url = String::from("https://objects.githubusercontent.com/test");
+            }
+        }
+
+        let results = correlate_downloads(&downloads[2..], &history, 3600.0);
+        assert_eq!(results[0].visits[0].visit_id, 279);
+        assert_eq!(results[0].visits[0].reason, CorrelationReason::MatchingHost);
+        assert!(results[0]
+            .visits
+            .iter()
+            .any(|visit| visit.reason == CorrelationReason::RedirectChain));
+        assert!(results[0]
+            .visits
+            .iter()
+            .any(|visit| visit.reason == CorrelationReason::ClosestVisit));
+    }
+
+    #[test]
+    fn test_correlate_downloads_no_history() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+
+        let results = correlate_downloads(&downloads, &[], 3600.0);
+        assert_eq!(results.len(), 3);
+        assert!(results[0].visits.is_empty());
+    }
+
+    #[test]
+    fn test_correlate_downloads_bad_window() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/History.db");
+        let history = SafariHistory::get_history(&test_location.display().to_string()).unwrap();
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+
+        assert!(correlate_downloads(&downloads, &history, 0.0).is_empty());
+        assert!(correlate_downloads(&downloads, &history, -60.0).is_empty());
+        assert!(correlate_downloads(&downloads, &history, f64::NAN).is_empty());
+    }
+}
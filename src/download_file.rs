@@ -0,0 +1,310 @@
+//! Check whether downloaded files still exist on disk
+//!
+//! Download paths can be resolved under the root of a mounted image instead of the live system.
+//! Paths containing `..` are rejected and symlinks are reported instead of followed, so a resolved path never leaves the image root
+
+use std::{
+    fs::{File, Metadata},
+    io::{BufReader, Read},
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::downloads::Downloads;
+
+#[derive(Debug, Serialize)]
+pub struct DownloadFile {
+    pub download_id: String,
+    pub download_path: String,
+    pub resolved_path: String, // Path checked on disk, includes the image root
+    pub exists: bool,
+    pub symlink: bool, // Download path is a symlink, the target is not checked
+    pub size: Option<u64>,
+    pub expected_size: i64, // Download size recorded by Safari
+    pub size_matches: bool,
+    pub created: Option<f64>,  // UNIX Epoch seconds
+    pub modified: Option<f64>, // UNIX Epoch seconds
+    pub accessed: Option<f64>, // UNIX Epoch seconds
+    pub sha256: Option<String>,
+}
+
+impl DownloadFile {
+    /// Check the files of all provided downloads under `image_root`. Use "/" for the live system.
+    /// Hashing reads the full file, only enable it when needed
+    pub fn check_downloads(
+        downloads: &[Downloads],
+        image_root: &str,
+        hash: bool,
+    ) -> Vec<DownloadFile> {
+        downloads
+            .iter()
+            .map(|download| DownloadFile::check_download(download, image_root, hash))
+            .collect()
+    }
+
+    /// Check the file of a single download under `image_root`
+    pub fn check_download(download: &Downloads, image_root: &str, hash: bool) -> DownloadFile {
+        let mut resolved_path = DownloadFile::resolve_path(image_root, &download.download_path);
+
+        // File may have been renamed in the Downloads folder, try the path from the bookmark data
+        if !DownloadFile::is_file_or_symlink(&resolved_path) {
+            if let Some(bookmark) = &download.bookmark {
+                if !bookmark.path.is_empty() {
+                    let bookmark_path =
+                        DownloadFile::resolve_path(image_root, &bookmark.path.join("/"));
+                    if DownloadFile::is_file_or_symlink(&bookmark_path) {
+                        resolved_path = bookmark_path;
+                    }
+                }
+            }
+        }
+
+        let mut download_file = DownloadFile {
+            download_id: download.download_id.clone(),
+            download_path: download.download_path.clone(),
+            resolved_path: match &resolved_path {
+                Some(path) => path.display().to_string(),
+                None => String::new(),
+            },
+            exists: false,
+            symlink: false,
+            size: None,
+            expected_size: download.download_bytes,
+            size_matches: false,
+            created: None,
+            modified: None,
+            accessed: None,
+            sha256: None,
+        };
+
+        let resolved_path = match resolved_path {
+            Some(path) => path,
+            None => return download_file,
+        };
+
+        // Do not follow symlinks, an absolute target would point at the analyst's own system
+        let metadata = match resolved_path.symlink_metadata() {
+            Ok(results) if results.file_type().is_symlink() => {
+                warn!(
+                    "Download file {} is a symlink, not following it",
+                    resolved_path.display()
+                );
+                download_file.symlink = true;
+                return download_file;
+            }
+            Ok(results) if results.is_file() => results,
+            _ => return download_file,
+        };
+
+        // A symlinked parent directory can still point outside of the image root
+        if !DownloadFile::is_in_image_root(&resolved_path, image_root) {
+            warn!(
+                "Download file {} resolves outside of image root {}",
+                resolved_path.display(),
+                image_root
+            );
+            return download_file;
+        }
+
+        download_file.exists = true;
+        download_file.size = Some(metadata.len());
+        download_file.size_matches = metadata.len() as i64 == download.download_bytes;
+        download_file.created = DownloadFile::get_timestamp(metadata.created());
+        download_file.modified = DownloadFile::get_timestamp(metadata.modified());
+        download_file.accessed = DownloadFile::get_timestamp(metadata.accessed());
+
+        if hash {
+            download_file.sha256 = DownloadFile::get_sha256(&resolved_path, &metadata);
+        }
+        download_file
+    }
+
+    // Join a download path to the image root. Paths with `..` components are rejected
+    fn resolve_path(image_root: &str, path: &str) -> Option<PathBuf> {
+        let download_path = Path::new(path.trim_start_matches('/'));
+        if download_path
+            .components()
+            .any(|component| component == Component::ParentDir)
+        {
+            warn!(
+                "Download path {} contains parent directory components",
+                path
+            );
+            return None;
+        }
+        Some(Path::new(image_root).join(download_path))
+    }
+
+    // Check if a path exists as a file or symlink, without following the symlink
+    fn is_file_or_symlink(path: &Option<PathBuf>) -> bool {
+        let metadata = match path {
+            Some(path) => path.symlink_metadata(),
+            None => return false,
+        };
+        match metadata {
+            Ok(results) => results.is_file() || results.file_type().is_symlink(),
+            Err(_) => false,
+        }
+    }
+
+    // Check if the canonical path is still under the canonical image root
+    fn is_in_image_root(path: &Path, image_root: &str) -> bool {
+        let canonical_root = match Path::new(image_root).canonicalize() {
+            Ok(results) => results,
+            Err(_) => return false,
+        };
+        match path.canonicalize() {
+            Ok(results) => results.starts_with(canonical_root),
+            Err(_) => false,
+        }
+    }
+
+    // Convert file metadata times to UNIX Epoch seconds. Not all filesystems support every timestamp
+    fn get_timestamp(time: std::io::Result<SystemTime>) -> Option<f64> {
+        let duration = time.ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(duration.as_secs_f64())
+    }
+
+    // Get the SHA256 hash of a file
+    fn get_sha256(path: &Path, metadata: &Metadata) -> Option<String> {
+        let file = match File::open(path) {
+            Ok(results) => results,
+            Err(err) => {
+                warn!("Failed to open download file {}: {:?}", path.display(), err);
+                return None;
+            }
+        };
+
+        let mut reader = BufReader::new(file);
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 65536];
+        let mut total_read = 0;
+        loop {
+            let bytes_read = match reader.read(&mut buffer) {
+                Ok(results) => results,
+                Err(err) => {
+                    warn!("Failed to read download file {}: {:?}", path.display(), err);
+                    return None;
+                }
+            };
+            if bytes_read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..bytes_read]);
+            total_read += bytes_read as u64;
+        }
+
+        if total_read != metadata.len() {
+            warn!(
+                "Download file {} changed while hashing, read {} of {} bytes",
+                path.display(),
+                total_read,
+                metadata.len()
+            );
+        }
+        Some(format!("{:x}", hasher.finalize()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::DownloadFile;
+    use crate::downloads::SafariDownloads;
+
+    #[test]
+    fn test_check_downloads() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/DownloadsImage");
+        let results =
+            DownloadFile::check_downloads(&downloads, &test_location.display().to_string(), true);
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].exists);
+        assert!(results[0]
+            .resolved_path
+            .ends_with("DownloadsImage/Users/puffycid/Downloads/powershell-7.2.5-osx-arm64.pkg"));
+        assert_eq!(results[0].size, Some(29));
+        assert_eq!(results[0].expected_size, 63055607);
+        assert!(!results[0].size_matches);
+        assert!(results[0].modified.is_some());
+        assert_eq!(
+            results[0].sha256.as_deref(),
+            Some("af09201213ec8a61ef7f569ae322614b0f579494a7d3d2eb039ecc62feb04a3e")
+        );
+
+        assert!(!results[1].exists);
+        assert_eq!(results[1].size, None);
+        assert_eq!(results[1].sha256, None);
+    }
+
+    #[test]
+    fn test_check_download_parent_dir() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let mut downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+        downloads[0].download_path =
+            String::from("/Users/puffycid/Downloads/../../../../../../../../etc/passwd");
+        downloads[0].bookmark = None;
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/DownloadsImage");
+        let result =
+            DownloadFile::check_download(&downloads[0], &test_location.display().to_string(), true);
+
+        assert!(!result.exists);
+        assert!(result.resolved_path.is_empty());
+        assert_eq!(result.sha256, None);
+    }
+
+    #[test]
+    fn test_check_download_symlink() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let mut downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+        // Absolute symlink in the image pointing at /etc/hosts
+        downloads[0].download_path = String::from("/Users/puffycid/Downloads/hosts.pkg");
+        downloads[0].bookmark = None;
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/DownloadsImage");
+        let result =
+            DownloadFile::check_download(&downloads[0], &test_location.display().to_string(), true);
+
+        assert!(!result.exists);
+        assert!(result.symlink);
+        assert_eq!(result.size, None);
+        assert_eq!(result.sha256, None);
+    }
+
+    #[test]
+    fn test_check_downloads_no_hash() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/DownloadsImage");
+        let result = DownloadFile::check_download(
+            &downloads[0],
+            &test_location.display().to_string(),
+            false,
+        );
+
+        assert!(result.exists);
+        assert_eq!(result.sha256, None);
+    }
+}
@@ -0,0 +1,428 @@
+//! Flag risky Safari downloads
+//!
+//! Downloads are scored by file type, filename tricks and where they were downloaded from.
+//! The rules can be loaded from a JSON file, any missing values use the defaults
+
+use std::{fs::read_to_string, net::IpAddr};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    download_url::DownloadUrl, downloads::Downloads, error::SafariError, history::History,
+    time::cocoa_to_unixepoch, url::get_host,
+};
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub enum RiskReason {
+    Executable,      // Executable extension or the bookmark executable flag
+    DiskImage,       // Disk image extension
+    Installer,       // Installer package extension
+    Script,          // Script extension
+    Archive,         // Archive extension
+    DoubleExtension, // Document extension followed by a risky extension (ex: invoice.pdf.app)
+    IpHost,          // Downloaded from a raw IP address
+    NewDomain,       // Download host was first visited within the new domain window
+    UnvisitedDomain, // Download host was never visited before the download
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RiskRules {
+    pub executable_extensions: Vec<String>,
+    pub disk_image_extensions: Vec<String>,
+    pub installer_extensions: Vec<String>,
+    pub script_extensions: Vec<String>,
+    pub archive_extensions: Vec<String>,
+    pub document_extensions: Vec<String>, // Extensions used to disguise a double extension
+    pub new_domain_seconds: f64, // Hosts first visited within this many seconds of the download are new
+    pub scores: RiskScores,
+}
+
+/// Score added for each reason. Set a score to 0 to disable the rule
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RiskScores {
+    pub executable: u32,
+    pub disk_image: u32,
+    pub installer: u32,
+    pub script: u32,
+    pub archive: u32,
+    pub double_extension: u32,
+    pub ip_host: u32,
+    pub new_domain: u32,
+    pub unvisited_domain: u32, // Disabled by default, downloads are often served from CDN hosts that are never visited
+}
+
+#[derive(Debug, Serialize)]
+pub struct DownloadRisk {
+    pub download_id: String,
+    pub download_path: String,
+    pub source_url: String,
+    pub score: u32,
+    pub reasons: Vec<RiskReason>,
+}
+
+impl Default for RiskRules {
+    fn default() -> Self {
+        let to_strings = |values: &[&str]| -> Vec<String> {
+            values.iter().map(|value| value.to_string()).collect()
+        };
+        RiskRules {
+            executable_extensions: to_strings(&["app", "bin", "command", "exe", "jar", "msi"]),
+            disk_image_extensions: to_strings(&[
+                "dmg",
+                "img",
+                "iso",
+                "sparsebundle",
+                "sparseimage",
+            ]),
+            installer_extensions: to_strings(&["mpkg", "pkg"]),
+            script_extensions: to_strings(&[
+                "applescript",
+                "js",
+                "pl",
+                "ps1",
+                "py",
+                "rb",
+                "scpt",
+                "sh",
+                "tool",
+            ]),
+            archive_extensions: to_strings(&["7z", "bz2", "gz", "rar", "tar", "tgz", "xz", "zip"]),
+            document_extensions: to_strings(&[
+                "doc", "docx", "jpeg", "jpg", "pdf", "png", "ppt", "pptx", "rtf", "txt", "xls",
+                "xlsx",
+            ]),
+            new_domain_seconds: 86400.0,
+            scores: RiskScores::default(),
+        }
+    }
+}
+
+impl Default for RiskScores {
+    fn default() -> Self {
+        RiskScores {
+            executable: 40,
+            disk_image: 25,
+            installer: 30,
+            script: 30,
+            archive: 10,
+            double_extension: 40,
+            ip_host: 30,
+            new_domain: 20,
+            unvisited_domain: 0,
+        }
+    }
+}
+
+impl RiskRules {
+    /// Load risk rules from a JSON file
+    pub fn from_file(path: &str) -> Result<RiskRules, SafariError> {
+        let data = match read_to_string(path) {
+            Ok(results) => results,
+            Err(err) => {
+                error!("Failed to read risk rules file {}: {:?}", path, err);
+                return Err(SafariError::Path);
+            }
+        };
+
+        match serde_json::from_str(&data) {
+            Ok(results) => Ok(results),
+            Err(err) => {
+                error!("Failed to parse risk rules file {}: {:?}", path, err);
+                Err(SafariError::Rules)
+            }
+        }
+    }
+}
+
+impl DownloadRisk {
+    /// Score all provided downloads. History is used to find newly seen domains and should come from the same user
+    pub fn score_downloads(
+        downloads: &[Downloads],
+        history: &[History],
+        rules: &RiskRules,
+    ) -> Vec<DownloadRisk> {
+        downloads
+            .iter()
+            .map(|download| DownloadRisk::score_download(download, history, rules))
+            .collect()
+    }
+
+    /// Score a single download
+    pub fn score_download(
+        download: &Downloads,
+        history: &[History],
+        rules: &RiskRules,
+    ) -> DownloadRisk {
+        let mut reasons: Vec<RiskReason> = Vec::new();
+
+        let extensions = DownloadRisk::get_extensions(download);
+        let last_extension = extensions.last().map(String::as_str).unwrap_or_default();
+        let has_extension = |values: &[String]| -> bool {
+            values
+                .iter()
+                .any(|value| value.eq_ignore_ascii_case(last_extension))
+        };
+
+        let is_executable =
+            matches!(&download.bookmark, Some(bookmark) if bookmark.has_executable_flag);
+        if is_executable || has_extension(&rules.executable_extensions) {
+            reasons.push(RiskReason::Executable);
+        }
+        if has_extension(&rules.disk_image_extensions) {
+            reasons.push(RiskReason::DiskImage);
+        }
+        if has_extension(&rules.installer_extensions) {
+            reasons.push(RiskReason::Installer);
+        }
+        if has_extension(&rules.script_extensions) {
+            reasons.push(RiskReason::Script);
+        }
+        if has_extension(&rules.archive_extensions) {
+            reasons.push(RiskReason::Archive);
+        }
+
+        // A document extension right before a risky extension. Only enabled extension rules count
+        let has_risky_extension = reasons
+            .iter()
+            .any(|reason| DownloadRisk::get_score(reason, &rules.scores) > 0);
+        if extensions.len() > 1 && has_risky_extension {
+            let previous_extension = &extensions[extensions.len() - 2];
+            if rules
+                .document_extensions
+                .iter()
+                .any(|value| value.eq_ignore_ascii_case(previous_extension))
+            {
+                reasons.push(RiskReason::DoubleExtension);
+            }
+        }
+
+        let host = get_host(&download.source_url);
+        if let Some(download_host) = &host {
+            if download_host.parse::<IpAddr>().is_ok() {
+                reasons.push(RiskReason::IpHost);
+            }
+        }
+        if let Some(reason) =
+            DownloadRisk::get_domain_reason(download, host, history, rules.new_domain_seconds)
+        {
+            reasons.push(reason);
+        }
+
+        let score = reasons
+            .iter()
+            .map(|reason| DownloadRisk::get_score(reason, &rules.scores))
+            .sum();
+        // Rules with a score of 0 are disabled
+        reasons.retain(|reason| DownloadRisk::get_score(reason, &rules.scores) > 0);
+
+        DownloadRisk {
+            download_id: download.download_id.clone(),
+            download_path: download.download_path.clone(),
+            source_url: download.source_url.clone(),
+            score,
+            reasons,
+        }
+    }
+
+    // Get the filename extensions of a download. Falls back to the filename in the source URL
+    fn get_extensions(download: &Downloads) -> Vec<String> {
+        let mut filename = download
+            .download_path
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        if filename.is_empty() {
+            filename = DownloadUrl::analyze(&download.source_url)
+                .filename
+                .unwrap_or_default();
+        }
+
+        // Skip the name itself and leading dots of hidden files
+        filename
+            .trim_start_matches('.')
+            .split('.')
+            .skip(1)
+            .map(|extension| extension.trim().to_string())
+            .collect()
+    }
+
+    // Check if the download host was first visited shortly before the download or was never visited at all
+    fn get_domain_reason(
+        download: &Downloads,
+        host: Option<String>,
+        history: &[History],
+        new_domain_seconds: f64,
+    ) -> Option<RiskReason> {
+        let download_time = download.download_entry_date?;
+        let download_host = host?;
+        // Cannot tell if a domain is new without any history
+        if history.is_empty() {
+            return None;
+        }
+
+        let first_visit = history
+            .iter()
+            .filter(|visit| get_host(&visit.url).as_deref() == Some(download_host.as_str()))
+            .map(|visit| cocoa_to_unixepoch(visit.visit_time))
+            .filter(|visit_time| *visit_time <= download_time)
+            .min_by(|first, second| first.total_cmp(second));
+
+        match first_visit {
+            Some(visit_time) if download_time - visit_time <= new_domain_seconds => {
+                Some(RiskReason::NewDomain)
+            }
+            Some(_) => None,
+            None => Some(RiskReason::UnvisitedDomain),
+        }
+    }
+
+    fn get_score(reason: &RiskReason, scores: &RiskScores) -> u32 {
+        match reason {
+            RiskReason::Executable => scores.executable,
+            RiskReason::DiskImage => scores.disk_image,
+            RiskReason::Installer => scores.installer,
+            RiskReason::Script => scores.script,
+            RiskReason::Archive => scores.archive,
+            RiskReason::DoubleExtension => scores.double_extension,
+            RiskReason::IpHost => scores.ip_host,
+            RiskReason::NewDomain => scores.new_domain,
+            RiskReason::UnvisitedDomain => scores.unvisited_domain,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{DownloadRisk, RiskReason, RiskRules};
+    use crate::{
+        downloads::{Downloads, SafariDownloads},
+        history::SafariHistory,
+        time::cocoa_to_unixepoch,
+        url::get_host,
+    };
+
+    fn get_test_downloads() -> Vec<Downloads> {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_score_downloads() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/History.db");
+        let history = SafariHistory::get_history(&test_location.display().to_string()).unwrap();
+        let downloads = get_test_downloads();
+
+        let results = DownloadRisk::score_downloads(&downloads, &history, &RiskRules::default());
+        assert_eq!(results.len(), 3);
+        assert!(results[0].reasons.contains(&RiskReason::Installer));
+        // objects.githubusercontent.com is never visited in the test history, which is disabled by default
+        assert!(!results[0].reasons.contains(&RiskReason::NewDomain));
+        assert!(!results[0].reasons.contains(&RiskReason::UnvisitedDomain));
+        assert!(!results[0].reasons.contains(&RiskReason::IpHost));
+        assert_eq!(results[0].score, 30);
+
+        let mut rules = RiskRules::default();
+        rules.scores.unvisited_domain = 5;
+        let results = DownloadRisk::score_downloads(&downloads, &history, &rules);
+        assert!(results[0].reasons.contains(&RiskReason::UnvisitedDomain));
+        assert_eq!(results[0].score, 35);
+    }
+
+    #[test]
+    fn test_score_download_new_domain() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/History.db");
+        let history = SafariHistory::get_history(&test_location.display().to_string()).unwrap();
+        let host = get_host(&history[0].url).unwrap();
+        let first_visit = history
+            .iter()
+            .filter(|visit| get_host(&visit.url).as_deref() == Some(host.as_str()))
+            .map(|visit| cocoa_to_unixepoch(visit.visit_time))
+            .min_by(|first, second| first.total_cmp(second))
+            .unwrap();
+
+        let mut download = get_test_downloads().remove(0);
+        download.source_url = format!("https://{}/download/setup.pkg", host);
+        download.download_entry_date = Some(first_visit + 60.0);
+        let result = DownloadRisk::score_download(&download, &history, &RiskRules::default());
+        assert!(result.reasons.contains(&RiskReason::NewDomain));
+
+        // Host was first visited long before the download
+        download.download_entry_date = Some(first_visit + 86400.0 * 30.0);
+        let result = DownloadRisk::score_download(&download, &history, &RiskRules::default());
+        assert!(!result.reasons.contains(&RiskReason::NewDomain));
+        assert!(!result.reasons.contains(&RiskReason::UnvisitedDomain));
+    }
+
+    #[test]
+    fn test_score_download_filename_tricks() {
+        let mut download = get_test_downloads().remove(0);
+        download.download_path = String::from("/Users/puffycid/Downloads/invoice.PDF.app");
+        download.source_url = String::from("http://192.168.1.10:8080/invoice.pdf.app");
+
+        let result = DownloadRisk::score_download(&download, &[], &RiskRules::default());
+        assert_eq!(
+            result.reasons,
+            [
+                RiskReason::Executable,
+                RiskReason::DoubleExtension,
+                RiskReason::IpHost
+            ]
+        );
+        assert_eq!(result.score, 110);
+    }
+
+    #[test]
+    fn test_score_download_disabled_rule() {
+        let mut download = get_test_downloads().remove(0);
+        download.download_path = String::from("/Users/puffycid/Downloads/test.zip");
+
+        let mut rules = RiskRules::default();
+        rules.scores.archive = 0;
+        let result = DownloadRisk::score_download(&download, &[], &rules);
+        assert!(result.reasons.is_empty());
+        assert_eq!(result.score, 0);
+    }
+
+    #[test]
+    fn test_score_download_disabled_double_extension() {
+        let mut download = get_test_downloads().remove(0);
+        download.download_path = String::from("/Users/puffycid/Downloads/invoice.pdf.app");
+
+        let mut rules = RiskRules::default();
+        rules.scores.executable = 0;
+        let result = DownloadRisk::score_download(&download, &[], &rules);
+        assert!(!result.reasons.contains(&RiskReason::DoubleExtension));
+        assert!(!result.reasons.contains(&RiskReason::Executable));
+        assert_eq!(result.score, 0);
+    }
+
+    #[test]
+    fn test_rules_from_file() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/risk_rules.json");
+        let rules = RiskRules::from_file(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(rules.installer_extensions, ["pkg"]);
+        assert_eq!(rules.scores.installer, 90);
+        // Missing values use the defaults
+        assert_eq!(rules.scores.archive, 10);
+        assert_eq!(rules.new_domain_seconds, 86400.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Rules")]
+    fn test_rules_from_bad_file() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/badfile.txt");
+        let _ = RiskRules::from_file(&test_location.display().to_string()).unwrap();
+    }
+}
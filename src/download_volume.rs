@@ -0,0 +1,174 @@
+//! Group Safari downloads by the volume they were saved to
+//!
+//! Volume details come from the download bookmark data. Downloads saved to volumes other than the filesystem root
+//! (USB drives, network shares) may indicate data being moved off the system
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::downloads::SafariDownloads;
+
+#[derive(Debug, Serialize)]
+pub struct VolumeSummary {
+    pub volume_uuid: String,
+    pub volume_name: String,
+    pub volume_path: String,
+    pub volume_url: String,
+    pub volume_size: i64,
+    pub volume_creation: f64,
+    pub volume_root: bool,      // If Volume is filesystem root
+    pub is_network: bool,       // Volume URL is not a local file URL (ex: smb://)
+    pub first_use: Option<f64>, // UNIX Epoch seconds
+    pub last_use: Option<f64>,  // UNIX Epoch seconds
+    pub users: Vec<String>,
+    pub downloads: Vec<VolumeDownload>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VolumeDownload {
+    pub download_id: String,
+    pub download_path: String,
+    pub download_time: Option<f64>, // UNIX Epoch seconds
+    pub user: String,
+    pub source: String, // Downloads file the entry was parsed from
+}
+
+impl VolumeSummary {
+    /// Group downloads from all users by volume UUID. Non-root volumes are listed first.
+    /// Downloads without bookmark data are skipped
+    pub fn summarize(downloads: &[SafariDownloads]) -> Vec<VolumeSummary> {
+        let mut volumes: BTreeMap<String, VolumeSummary> = BTreeMap::new();
+        for safari_downloads in downloads {
+            for download in &safari_downloads.results {
+                let bookmark = match &download.bookmark {
+                    Some(results) => results,
+                    None => continue,
+                };
+
+                // Older bookmarks may not have a volume UUID
+                let volume_key = if bookmark.volume_uuid.is_empty() {
+                    bookmark.volume_path.clone()
+                } else {
+                    bookmark.volume_uuid.clone()
+                };
+                let volume = volumes.entry(volume_key).or_insert_with(|| VolumeSummary {
+                    volume_uuid: bookmark.volume_uuid.clone(),
+                    volume_name: bookmark.volume_name.clone(),
+                    volume_path: bookmark.volume_path.clone(),
+                    volume_url: bookmark.volume_url.clone(),
+                    volume_size: bookmark.volume_size,
+                    volume_creation: bookmark.volume_creation,
+                    volume_root: bookmark.volume_root,
+                    is_network: !bookmark.volume_url.is_empty()
+                        && !bookmark.volume_url.starts_with("file://"),
+                    first_use: None,
+                    last_use: None,
+                    users: Vec::new(),
+                    downloads: Vec::new(),
+                });
+
+                let download_time = download
+                    .download_entry_date
+                    .or(download.download_entry_finish);
+                if let Some(time) = download_time {
+                    volume.first_use = Some(
+                        volume
+                            .first_use
+                            .map_or(time, |first_use| first_use.min(time)),
+                    );
+                    volume.last_use =
+                        Some(volume.last_use.map_or(time, |last_use| last_use.max(time)));
+                }
+                if !volume.users.contains(&safari_downloads.user) {
+                    volume.users.push(safari_downloads.user.clone());
+                }
+
+                volume.downloads.push(VolumeDownload {
+                    download_id: download.download_id.clone(),
+                    download_path: download.download_path.clone(),
+                    download_time,
+                    user: safari_downloads.user.clone(),
+                    source: safari_downloads.path.clone(),
+                });
+            }
+        }
+
+        let mut summary: Vec<VolumeSummary> = volumes.into_values().collect();
+        summary.sort_by_key(|volume| volume.volume_root);
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::VolumeSummary;
+    use crate::downloads::SafariDownloads;
+
+    fn get_test_downloads(user: &str) -> SafariDownloads {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let path = test_location.display().to_string();
+        let mut downloads = SafariDownloads {
+            results: SafariDownloads::get_downloads(&path).unwrap(),
+            path,
+            user: user.to_string(),
+        };
+
+        for download in downloads.results.iter_mut() {
+            let bookmark = download.bookmark.as_mut().unwrap();
+            bookmark.volume_uuid = String::from("96FB41C0-6CE9-4DA2-8435-35BC19C735A3");
+            bookmark.volume_name = String::from("Macintosh HD");
+            bookmark.volume_path = String::from("/");
+            bookmark.volume_url = String::from("file:///");
+            bookmark.volume_root = true;
+        }
+        downloads
+    }
+
+    #[test]
+    fn test_summarize() {
+        let mut downloads = get_test_downloads("puffycid");
+        let bookmark = downloads.results[2].bookmark.as_mut().unwrap();
+        bookmark.volume_uuid = String::from("0F6C5F0E-3B4A-4C2B-9F1D-2A7E8B9C0D1E");
+        bookmark.volume_name = String::from("USB");
+        bookmark.volume_path = String::from("/Volumes/USB");
+        bookmark.volume_url = String::from("file:///Volumes/USB/");
+        bookmark.volume_root = false;
+
+        let results = VolumeSummary::summarize(&[downloads, get_test_downloads("bob")]);
+        assert_eq!(results.len(), 2);
+
+        assert_eq!(results[0].volume_name, "USB");
+        assert!(!results[0].volume_root);
+        assert!(!results[0].is_network);
+        assert_eq!(results[0].downloads.len(), 1);
+        assert_eq!(results[0].users, ["puffycid"]);
+        assert_eq!(results[0].first_use, Some(1656265410.020717));
+        assert_eq!(results[0].last_use, Some(1656265410.020717));
+
+        assert_eq!(results[1].volume_name, "Macintosh HD");
+        assert!(results[1].volume_root);
+        assert_eq!(results[1].downloads.len(), 5);
+        assert_eq!(results[1].users, ["puffycid", "bob"]);
+        assert_eq!(results[1].first_use, Some(1656265410.020717));
+        assert_eq!(results[1].last_use, Some(1656266417.8241808));
+    }
+
+    #[test]
+    fn test_summarize_network_volume() {
+        let mut downloads = get_test_downloads("puffycid");
+        let bookmark = downloads.results[0].bookmark.as_mut().unwrap();
+        bookmark.volume_uuid = String::new();
+        bookmark.volume_path = String::from("/Volumes/share");
+        bookmark.volume_url = String::from("smb://fileserver/share");
+        bookmark.volume_root = false;
+
+        let results = VolumeSummary::summarize(&[downloads]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].volume_path, "/Volumes/share");
+        assert!(results[0].is_network);
+    }
+}
@@ -1,9 +1,10 @@
-use std::{fs::read_dir, path::Path};
+use std::collections::BTreeMap;
 
-use log::{error, info, warn};
+use log::warn;
+use plist::Value;
 use serde::Serialize;
 
-use crate::{downloads_plist::DownloadsPlist, error::SafariError, size::get_file_size};
+use crate::{downloads_plist::DownloadsPlist, error::SafariError, users::get_user_files};
 
 #[derive(Debug, Serialize)]
 pub struct Downloads {
@@ -11,9 +12,19 @@ pub struct Downloads {
     pub download_path: String,
     pub sandbox_id: String,
     pub download_bytes: i64,
+    pub download_bytes_so_far: i64,
+    pub remove_when_done: bool, // Safari removes the entry from the downloads list once finished
+    pub use_request_url_as_origin: bool,
+    pub status: DownloadStatus,
     pub download_id: String,
-    pub download_entry_date: u64,
-    pub download_entry_finish: u64,
+    pub download_entry_date: Option<f64>,   // UNIX Epoch seconds
+    pub download_entry_finish: Option<f64>, // UNIX Epoch seconds
+    pub bookmark: Option<DownloadBookmark>, // Missing if the bookmark blob is absent or could not be parsed
+    pub unknown_values: BTreeMap<String, Value>, // PLIST keys not recognized by the parser
+}
+
+#[derive(Debug, Serialize)]
+pub struct DownloadBookmark {
     pub path: Vec<String>,             // Path to binary to run
     pub cnid_path: Vec<i64>,           // Path represented as Catalog Node ID
     pub creation: f64,                 // Created timestamp of binary target
@@ -37,6 +48,13 @@ pub struct Downloads {
     pub file_ref_flag: bool,
 }
 
+#[derive(Debug, Serialize, PartialEq)]
+pub enum DownloadStatus {
+    Complete,
+    Partial,
+    Unknown, // Download was cancelled or has no progress
+}
+
 #[derive(Debug, Serialize)]
 pub struct SafariDownloads {
     pub results: Vec<Downloads>,
@@ -47,124 +65,127 @@ pub struct SafariDownloads {
 impl SafariDownloads {
     /// Get Safari Downloads PLIST file for all users
     pub fn get_users_downloads() -> Result<Vec<SafariDownloads>, SafariError> {
-        let base_directory = "/Users/";
-        let downloads_path = "/Library/Safari/Downloads.plist";
-        let users = read_dir(base_directory);
+        let user_files = get_user_files("/Library/Safari/Downloads.plist")?;
 
         let mut safari_downloads: Vec<SafariDownloads> = Vec::new();
-        match users {
-            Ok(dir) => {
-                for entry in dir {
-                    match entry {
-                        Ok(entry_result) => {
-                            let path =
-                                format!("{}{}", entry_result.path().display(), downloads_path);
-                            let full_path = Path::new(&path);
-                            // Make sure the downloads file exists
-                            if !full_path.is_file() || !get_file_size(&path) {
-                                continue;
-                            }
-                            info!("Parsing file path: {}", path);
-
-                            let results =
-                                SafariDownloads::get_downloads(&full_path.display().to_string())?;
-                            let username = entry_result
-                                .path()
-                                .display()
-                                .to_string()
-                                .replace("/Users/", "");
-
-                            let downloads = SafariDownloads {
-                                results,
-                                path,
-                                user: username,
-                            };
-                            safari_downloads.push(downloads);
-                        }
-                        Err(err) => warn!("Failed to get user directory: {:?}", err),
-                    }
-                }
-            }
-            Err(err) => {
-                error!(
-                    "Failed to read base directory {}: {:?}",
-                    base_directory, err
-                );
-                return Err(SafariError::Path);
-            }
+        for user_file in user_files {
+            let results = SafariDownloads::get_downloads(&user_file.path)?;
+            let downloads = SafariDownloads {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_downloads.push(downloads);
         }
-
         Ok(safari_downloads)
     }
 
     /// Parse the Safari Downloads PLIST file
     pub fn get_downloads(path: &str) -> Result<Vec<Downloads>, SafariError> {
         // Parse the initial binary PLIST file
-        let downloads_results = DownloadsPlist::parse_safari_plist(path);
-        let downloads_data = match downloads_results {
-            Ok(results) => results,
-            Err(err) => {
-                error!("Failed to parse PLIST file at {}: {:?}", path, err);
-                return Err(SafariError::Plist);
-            }
-        };
+        let downloads_data = DownloadsPlist::parse_safari_plist(path)?;
         let mut safari_downloads: Vec<Downloads> = Vec::new();
 
         for data in downloads_data {
-            // Parse the Bookmarks blob. Contains similar data as the PLIST file
-            let bookmark_results = macos_bookmarks::parser::parse_bookmark(&data.bookmark_blob);
-
-            let bookmark = match bookmark_results {
-                Ok(results) => results,
-                Err(err) => {
-                    error!(
-                        "Failed to parse Safari downloads bookmark data at {}: {:?}",
-                        path, err
-                    );
-                    return Err(SafariError::Plist);
-                }
-            };
+            let bookmark = SafariDownloads::get_bookmark(&data.bookmark_blob, path);
             let safari_data = Downloads {
                 source_url: data.download_url,
                 download_path: data.download_path,
                 sandbox_id: data.download_sandbox_id,
                 download_bytes: data.download_entry_progress_total_to_load,
+                download_bytes_so_far: data.download_entry_progress_bytes_so_far,
+                remove_when_done: data.download_remove_when_done,
+                use_request_url_as_origin: data.download_entry_should_use_request_url_as_origin,
+                status: SafariDownloads::get_status(
+                    data.download_entry_progress_bytes_so_far,
+                    data.download_entry_progress_total_to_load,
+                    data.download_entry_date_finished_key,
+                ),
                 download_id: data.download_identifier,
                 download_entry_date: data.download_entry_date_added_key,
                 download_entry_finish: data.download_entry_date_finished_key,
-                path: bookmark.path,
-                cnid_path: bookmark.cnid_path,
-                creation: bookmark.creation,
-                volume_path: bookmark.volume_path,
-                volume_url: bookmark.volume_url,
-                volume_name: bookmark.volume_name,
-                volume_uuid: bookmark.volume_uuid,
-                volume_size: bookmark.volume_size,
-                volume_creation: bookmark.volume_creation,
-                volume_flag: bookmark.volume_flag,
-                volume_root: bookmark.volume_root,
-                localized_name: bookmark.localized_name,
-                security_extension_rw: bookmark.security_extension_rw,
-                security_extension_ro: bookmark.security_extension_ro,
-                target_flags: bookmark.target_flags,
-                username: bookmark.username,
-                folder_index: bookmark.folder_index,
-                uid: bookmark.uid,
-                creation_options: bookmark.creation_options,
-                has_executable_flag: bookmark.is_executable,
-                file_ref_flag: bookmark.file_ref_flag,
+                bookmark,
+                unknown_values: data.unknown_values,
             };
             safari_downloads.push(safari_data);
         }
         Ok(safari_downloads)
     }
+
+    /// Get all unknown PLIST keys seen in the downloads and how many downloads contained them
+    pub fn get_unknown_keys(downloads: &[Downloads]) -> BTreeMap<String, usize> {
+        let mut unknown_keys: BTreeMap<String, usize> = BTreeMap::new();
+        for download in downloads {
+            for key in download.unknown_values.keys() {
+                *unknown_keys.entry(key.to_string()).or_insert(0) += 1;
+            }
+        }
+        unknown_keys
+    }
+
+    /// Parse the Bookmarks blob. Contains similar data as the PLIST file
+    fn get_bookmark(bookmark_blob: &[u8], path: &str) -> Option<DownloadBookmark> {
+        if bookmark_blob.is_empty() {
+            warn!("No Safari downloads bookmark data at {}", path);
+            return None;
+        }
+
+        let bookmark_results = macos_bookmarks::parser::parse_bookmark(bookmark_blob);
+        let bookmark = match bookmark_results {
+            Ok(results) => results,
+            Err(err) => {
+                warn!(
+                    "Failed to parse Safari downloads bookmark data at {}: {:?}",
+                    path, err
+                );
+                return None;
+            }
+        };
+
+        let download_bookmark = DownloadBookmark {
+            path: bookmark.path,
+            cnid_path: bookmark.cnid_path,
+            creation: bookmark.creation,
+            volume_path: bookmark.volume_path,
+            volume_url: bookmark.volume_url,
+            volume_name: bookmark.volume_name,
+            volume_uuid: bookmark.volume_uuid,
+            volume_size: bookmark.volume_size,
+            volume_creation: bookmark.volume_creation,
+            volume_flag: bookmark.volume_flag,
+            volume_root: bookmark.volume_root,
+            localized_name: bookmark.localized_name,
+            security_extension_rw: bookmark.security_extension_rw,
+            security_extension_ro: bookmark.security_extension_ro,
+            target_flags: bookmark.target_flags,
+            username: bookmark.username,
+            folder_index: bookmark.folder_index,
+            uid: bookmark.uid,
+            creation_options: bookmark.creation_options,
+            has_executable_flag: bookmark.is_executable,
+            file_ref_flag: bookmark.file_ref_flag,
+        };
+        Some(download_bookmark)
+    }
+
+    /// Determine if a download completed based on its progress and finish time
+    fn get_status(bytes_so_far: i64, total_bytes: i64, finished: Option<f64>) -> DownloadStatus {
+        // Total bytes can be 0 if the server did not provide a size
+        if bytes_so_far > 0 && bytes_so_far >= total_bytes && finished.is_some() {
+            return DownloadStatus::Complete;
+        }
+        if bytes_so_far > 0 {
+            return DownloadStatus::Partial;
+        }
+        DownloadStatus::Unknown
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
-    use crate::downloads::SafariDownloads;
+    use crate::downloads::{DownloadStatus, SafariDownloads};
 
     #[test]
     #[ignore = "Get live users Safari downloads"]
@@ -191,14 +212,22 @@ mod tests {
             "DBA9EBA4-D23B-43C5-9DEB-131566E7BD8B"
         );
         assert_eq!(results[0].download_bytes, 63055607);
+        assert_eq!(results[0].download_bytes_so_far, 63055607);
+        assert!(!results[0].remove_when_done);
+        assert!(!results[0].use_request_url_as_origin);
+        assert_eq!(results[0].status, DownloadStatus::Complete);
         assert_eq!(
             results[0].download_id,
             "835D414A-492E-4DBB-BD6B-E8FACD4ED84D"
         );
-        assert_eq!(results[0].download_entry_date, 1656266417);
-        assert_eq!(results[0].download_entry_finish, 1656266422);
+        assert_eq!(results[0].download_entry_date, Some(1656266417.8241808));
+        assert_eq!(results[0].download_entry_finish, Some(1656266422.0560899));
+        assert!(results[0].unknown_values.is_empty());
+        assert!(SafariDownloads::get_unknown_keys(&results).is_empty());
+
+        let bookmark = results[0].bookmark.as_ref().unwrap();
         assert_eq!(
-            results[0].path,
+            bookmark.path,
             [
                 "Users",
                 "puffycid",
@@ -206,28 +235,58 @@ mod tests {
                 "powershell-7.2.5-osx-arm64.pkg"
             ]
         );
-        assert_eq!(results[0].cnid_path, [21327, 360459, 360510, 37719400]);
-        assert_eq!(results[0].volume_path, "/");
-        assert_eq!(results[0].creation, 677959217.8519708);
-        assert_eq!(results[0].volume_url, "file:///");
-        assert_eq!(results[0].volume_name, "Macintosh HD");
+        assert_eq!(bookmark.cnid_path, [21327, 360459, 360510, 37719400]);
+        assert_eq!(bookmark.volume_path, "/");
+        assert_eq!(bookmark.creation, 677959217.8519708);
+        assert_eq!(bookmark.volume_url, "file:///");
+        assert_eq!(bookmark.volume_name, "Macintosh HD");
+        assert_eq!(bookmark.volume_uuid, "96FB41C0-6CE9-4DA2-8435-35BC19C735A3");
+        assert_eq!(bookmark.volume_size, 2000662327296);
+        assert_eq!(bookmark.volume_flag, [4294967425, 4294972399, 0]);
+        assert_eq!(bookmark.volume_creation, 667551907.0);
+        assert_eq!(bookmark.volume_root, true);
+        assert_eq!(bookmark.localized_name, "");
+        assert_eq!(bookmark.security_extension_ro, "");
+        assert_eq!(bookmark.security_extension_rw, "");
+        assert_eq!(bookmark.target_flags, [1, 15, 0]);
+        assert_eq!(bookmark.username, "puffycid");
+        assert_eq!(bookmark.folder_index, 2);
+        assert_eq!(bookmark.uid, 501);
+        assert_eq!(bookmark.creation_options, 671094784);
+        assert_eq!(bookmark.has_executable_flag, false);
+        assert_eq!(bookmark.file_ref_flag, false);
+    }
+
+    #[test]
+    fn test_get_status() {
         assert_eq!(
-            results[0].volume_uuid,
-            "96FB41C0-6CE9-4DA2-8435-35BC19C735A3"
+            SafariDownloads::get_status(100, 100, Some(1656266422.05609)),
+            DownloadStatus::Complete
         );
-        assert_eq!(results[0].volume_size, 2000662327296);
-        assert_eq!(results[0].volume_flag, [4294967425, 4294972399, 0]);
-        assert_eq!(results[0].volume_creation, 667551907.0);
-        assert_eq!(results[0].volume_root, true);
-        assert_eq!(results[0].localized_name, "");
-        assert_eq!(results[0].security_extension_ro, "");
-        assert_eq!(results[0].security_extension_rw, "");
-        assert_eq!(results[0].target_flags, [1, 15, 0]);
-        assert_eq!(results[0].username, "puffycid");
-        assert_eq!(results[0].folder_index, 2);
-        assert_eq!(results[0].uid, 501);
-        assert_eq!(results[0].creation_options, 671094784);
-        assert_eq!(results[0].has_executable_flag, false);
-        assert_eq!(results[0].file_ref_flag, false);
+        assert_eq!(
+            SafariDownloads::get_status(100, 0, Some(1656266422.05609)),
+            DownloadStatus::Complete
+        );
+        assert_eq!(
+            SafariDownloads::get_status(50, 100, None),
+            DownloadStatus::Partial
+        );
+        assert_eq!(
+            SafariDownloads::get_status(100, 100, None),
+            DownloadStatus::Partial
+        );
+        assert_eq!(
+            SafariDownloads::get_status(0, 100, None),
+            DownloadStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_get_bookmark() {
+        let result = SafariDownloads::get_bookmark(&[], "test");
+        assert!(result.is_none());
+
+        let result = SafariDownloads::get_bookmark(&[1, 2, 3, 4], "test");
+        assert!(result.is_none());
     }
 }
@@ -1,27 +1,43 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+//! Parse the Safari Downloads PLIST file
+//!
+//! Returns the raw PLIST entries, including the unparsed macOS Bookmark blob.
+//! Use `SafariDownloads` to also parse the bookmark data
 
-use log::warn;
+use std::collections::BTreeMap;
+
+use log::{error, warn};
 use plist::{Dictionary, Value};
+use serde::Serialize;
+
+use crate::{error::SafariError, plist_values::get_date_value};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DownloadsPlist {
-    pub bookmark_blob: Vec<u8>,
+    pub bookmark_blob: Vec<u8>, // macOS Bookmark data, empty if the entry had none
     pub download_entry_progress_total_to_load: i64,
     pub download_entry_progress_bytes_so_far: i64,
-    pub download_entry_date_added_key: u64,
-    pub download_entry_date_finished_key: u64,
+    pub download_entry_date_added_key: Option<f64>, // UNIX Epoch seconds
+    pub download_entry_date_finished_key: Option<f64>, // UNIX Epoch seconds
     pub download_entry_should_use_request_url_as_origin: bool,
     pub download_identifier: String,
     pub download_url: String,
     pub download_path: String,
     pub download_sandbox_id: String,
     pub download_remove_when_done: bool,
+    pub unknown_values: BTreeMap<String, Value>, // Keys not recognized by the parser
 }
 
 impl DownloadsPlist {
-    /// Parse a PLIST file at provded path
-    pub fn parse_safari_plist(path: &str) -> Result<Vec<DownloadsPlist>, plist::Error> {
-        let downloads: Dictionary = plist::from_file(path)?;
+    /// Parse a Safari Downloads PLIST file at provided path
+    pub fn parse_safari_plist(path: &str) -> Result<Vec<DownloadsPlist>, SafariError> {
+        let downloads_results: Result<Dictionary, plist::Error> = plist::from_file(path);
+        let downloads = match downloads_results {
+            Ok(results) => results,
+            Err(err) => {
+                error!("Failed to parse PLIST file at {}: {:?}", path, err);
+                return Err(SafariError::Plist);
+            }
+        };
         let mut downloads_data: Vec<DownloadsPlist> = Vec::new();
         for (key, value) in downloads {
             if key != "DownloadHistory" {
@@ -31,7 +47,7 @@ impl DownloadsPlist {
             match value {
                 Value::Array(_) => {
                     // Parse the array of dictionaries
-                    downloads_data = DownloadsPlist::get_array_values(value)?;
+                    downloads_data = DownloadsPlist::get_array_values(value);
                 }
                 _ => {
                     warn!("Empty PLIST Array data");
@@ -43,7 +59,7 @@ impl DownloadsPlist {
     }
 
     /// Loop through Array values and get the downloads metadata
-    fn get_array_values(value: Value) -> Result<Vec<DownloadsPlist>, plist::Error> {
+    fn get_array_values(value: Value) -> Vec<DownloadsPlist> {
         let mut downloads_data: Vec<DownloadsPlist> = Vec::new();
         let results = value.into_array();
         match results {
@@ -57,14 +73,15 @@ impl DownloadsPlist {
                                 bookmark_blob: Vec::new(),
                                 download_entry_progress_total_to_load: 0,
                                 download_entry_progress_bytes_so_far: 0,
-                                download_entry_date_added_key: 0,
-                                download_entry_date_finished_key: 0,
+                                download_entry_date_added_key: None,
+                                download_entry_date_finished_key: None,
                                 download_entry_should_use_request_url_as_origin: false,
                                 download_identifier: String::new(),
                                 download_url: String::new(),
                                 download_path: String::new(),
                                 download_sandbox_id: String::new(),
                                 download_remove_when_done: false,
+                                unknown_values: BTreeMap::new(),
                             };
                             match dict_bookmark {
                                 Some(dict) => {
@@ -82,7 +99,10 @@ impl DownloadsPlist {
                                             "DownloadEntryPath" => downloads_metadata.download_path = DownloadsPlist::get_string_value(dict_data),
                                             "DownloadEntrySandboxIdentifier" => downloads_metadata.download_sandbox_id = DownloadsPlist::get_string_value(dict_data),
                                             "DownloadEntryRemoveWhenDoneKey" => downloads_metadata.download_remove_when_done = DownloadsPlist::get_boolean_value(dict_data),
-                                            _ => warn!("Unknown Safari download key: {}", dict_key)
+                                            _ => {
+                                                warn!("Unknown Safari download key: {}", dict_key);
+                                                downloads_metadata.unknown_values.insert(dict_key.to_string(), dict_data.clone());
+                                            }
                                         }
                                     }
                                     downloads_data.push(downloads_metadata);
@@ -94,9 +114,9 @@ impl DownloadsPlist {
                     }
                 }
             }
-            None => return Ok(downloads_data),
+            None => return downloads_data,
         }
-        Ok(downloads_data)
+        downloads_data
     }
 
     // Get the string value from the dictionary
@@ -148,33 +168,23 @@ impl DownloadsPlist {
     }
 
     // Safari uses Apple Cocoa Core Data timestamp. Number of seconds since 2001-01-01 00:00:00 UTC
-    // The Rust PLIST crate handles converting to UNIX Epoch seconds
-    fn get_safari_timestamp(dict_data: &Value) -> u64 {
-        let date_added_results = dict_data.as_date();
-        let date_added = match date_added_results {
-            Some(results) => results,
-            None => {
-                warn!("No timestamp in PLIST file");
-                return 0;
-            }
-        };
-        let date_time: SystemTime = date_added.into();
-        let epoch_time_results = date_time.duration_since(UNIX_EPOCH);
-        let epoch_time = match epoch_time_results {
-            Ok(results) => results,
-            Err(err) => {
-                warn!("Failed to parse Timestamp in PLIST file: {:?}", err);
-                return 0;
-            }
-        };
-        epoch_time.as_secs()
+    // The Rust PLIST crate handles converting to UNIX Epoch, sub-second precision is kept
+    fn get_safari_timestamp(dict_data: &Value) -> Option<f64> {
+        let timestamp = get_date_value(dict_data);
+        if timestamp.is_none() {
+            warn!("No timestamp in PLIST file");
+        }
+        timestamp
     }
 }
 
 #[cfg(test)]
 mod tests {
     use plist::{Dictionary, Value};
-    use std::{path::PathBuf, time::UNIX_EPOCH};
+    use std::{
+        path::PathBuf,
+        time::{Duration, UNIX_EPOCH},
+    };
 
     use super::DownloadsPlist;
 
@@ -220,8 +230,14 @@ mod tests {
         assert_eq!(results[0].bookmark_blob, bookmark_blob);
         assert_eq!(results[0].download_entry_progress_total_to_load, 63055607);
         assert_eq!(results[0].download_entry_progress_bytes_so_far, 63055607);
-        assert_eq!(results[0].download_entry_date_added_key, 1656266417);
-        assert_eq!(results[0].download_entry_date_finished_key, 1656266422);
+        assert_eq!(
+            results[0].download_entry_date_added_key,
+            Some(1656266417.8241808)
+        );
+        assert_eq!(
+            results[0].download_entry_date_finished_key,
+            Some(1656266422.0560899)
+        );
         assert_eq!(
             results[0].download_entry_should_use_request_url_as_origin,
             false
@@ -275,8 +291,14 @@ mod tests {
         assert_eq!(results[1].bookmark_blob, bookmark_blob);
         assert_eq!(results[1].download_entry_progress_total_to_load, 66784330);
         assert_eq!(results[1].download_entry_progress_bytes_so_far, 66784330);
-        assert_eq!(results[1].download_entry_date_added_key, 1656266411);
-        assert_eq!(results[1].download_entry_date_finished_key, 1656266415);
+        assert_eq!(
+            results[1].download_entry_date_added_key,
+            Some(1656266411.403398)
+        );
+        assert_eq!(
+            results[1].download_entry_date_finished_key,
+            Some(1656266415.932024)
+        );
         assert_eq!(
             results[1].download_entry_should_use_request_url_as_origin,
             false
@@ -330,8 +352,14 @@ mod tests {
         assert_eq!(results[2].bookmark_blob, bookmark_blob);
         assert_eq!(results[2].download_entry_progress_total_to_load, 66784330);
         assert_eq!(results[2].download_entry_progress_bytes_so_far, 66784330);
-        assert_eq!(results[2].download_entry_date_added_key, 1656265410);
-        assert_eq!(results[2].download_entry_date_finished_key, 1656265414);
+        assert_eq!(
+            results[2].download_entry_date_added_key,
+            Some(1656265410.020717)
+        );
+        assert_eq!(
+            results[2].download_entry_date_finished_key,
+            Some(1656265414.753056)
+        );
         assert_eq!(
             results[2].download_entry_should_use_request_url_as_origin,
             false
@@ -350,6 +378,7 @@ mod tests {
             "7FFFC852-3A18-4978-8949-0CFFABF7776A"
         );
         assert_eq!(results[2].download_remove_when_done, false);
+        assert!(results[2].unknown_values.is_empty());
     }
 
     #[test]
@@ -367,7 +396,7 @@ mod tests {
             match value {
                 Value::Array(_) => {
                     // Parse the array of dictionaries
-                    results = DownloadsPlist::get_array_values(value).unwrap();
+                    results = DownloadsPlist::get_array_values(value);
                 }
                 _ => {
                     panic!("Empty PLIST Array data");
@@ -408,8 +437,14 @@ mod tests {
         assert_eq!(results[0].bookmark_blob, bookmark_blob);
         assert_eq!(results[0].download_entry_progress_total_to_load, 63055607);
         assert_eq!(results[0].download_entry_progress_bytes_so_far, 63055607);
-        assert_eq!(results[0].download_entry_date_added_key, 1656266417);
-        assert_eq!(results[0].download_entry_date_finished_key, 1656266422);
+        assert_eq!(
+            results[0].download_entry_date_added_key,
+            Some(1656266417.8241808)
+        );
+        assert_eq!(
+            results[0].download_entry_date_finished_key,
+            Some(1656266422.0560899)
+        );
         assert_eq!(
             results[0].download_entry_should_use_request_url_as_origin,
             false
@@ -463,8 +498,14 @@ mod tests {
         assert_eq!(results[1].bookmark_blob, bookmark_blob);
         assert_eq!(results[1].download_entry_progress_total_to_load, 66784330);
         assert_eq!(results[1].download_entry_progress_bytes_so_far, 66784330);
-        assert_eq!(results[1].download_entry_date_added_key, 1656266411);
-        assert_eq!(results[1].download_entry_date_finished_key, 1656266415);
+        assert_eq!(
+            results[1].download_entry_date_added_key,
+            Some(1656266411.403398)
+        );
+        assert_eq!(
+            results[1].download_entry_date_finished_key,
+            Some(1656266415.932024)
+        );
         assert_eq!(
             results[1].download_entry_should_use_request_url_as_origin,
             false
@@ -485,6 +526,29 @@ mod tests {
         assert_eq!(results[1].download_remove_when_done, false);
     }
 
+    #[test]
+    fn test_get_array_values_unknown_keys() {
+        let mut dict = Dictionary::new();
+        dict.insert(
+            String::from("DownloadEntryURL"),
+            Value::String(String::from("https://example.com/file.zip")),
+        );
+        dict.insert(
+            String::from("DownloadEntryOriginURL"),
+            Value::String(String::from("https://example.com")),
+        );
+        let value = Value::Array(vec![Value::Dictionary(dict)]);
+
+        let results = DownloadsPlist::get_array_values(value);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].download_url, "https://example.com/file.zip");
+        assert_eq!(results[0].unknown_values.len(), 1);
+        assert_eq!(
+            results[0].unknown_values["DownloadEntryOriginURL"],
+            Value::String(String::from("https://example.com"))
+        );
+    }
+
     #[test]
     fn test_get_string_value() {
         let test: Value = Value::String(String::from("test"));
@@ -522,6 +586,16 @@ mod tests {
         let test: Value = Value::Date(plist::Date::from(UNIX_EPOCH));
         let results = DownloadsPlist::get_safari_timestamp(&test);
 
-        assert_eq!(results, 0);
+        assert_eq!(results, Some(0.0));
+
+        let test: Value = Value::Date(plist::Date::from(UNIX_EPOCH - Duration::from_millis(1500)));
+        let results = DownloadsPlist::get_safari_timestamp(&test);
+
+        assert_eq!(results, Some(-1.5));
+
+        let test: Value = Value::Boolean(false);
+        let results = DownloadsPlist::get_safari_timestamp(&test);
+
+        assert_eq!(results, None);
     }
 }
@@ -8,6 +8,8 @@ pub enum SafariError {
     NoHistory,
     Plist,
     Bookmark,
+    Rules,
+    Cookies,
 }
 
 impl std::error::Error for SafariError {}
@@ -20,6 +22,8 @@ impl fmt::Display for SafariError {
             SafariError::BadSQL => write!(f, "Could not compose sqlite query"),
             SafariError::Plist => write!(f, "Could not parse PLIST file"),
             SafariError::Bookmark => write!(f, "Could not parse PLIST bookmark data"),
+            SafariError::Rules => write!(f, "Could not parse risk rules file"),
+            SafariError::Cookies => write!(f, "Could not parse binary cookies file"),
             SafariError::SqliteParse => {
                 write!(f, "Failed to parse SQLITE History file")
             }
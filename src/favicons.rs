@@ -0,0 +1,205 @@
+//! Parse the Safari favicons SQLITE file
+//!
+//! Maps page URLs to their icon URLs. Entries are not removed when History is cleared.
+//! Cached icon images are stored in the `favicons` directory next to favicons.db, named by the icon UUID
+
+use std::{fs::read, path::Path};
+
+use log::{error, warn};
+use serde::Serialize;
+
+use crate::{
+    error::SafariError, sqlite::open_sqlite, time::cocoa_to_unixepoch, users::get_user_files,
+};
+
+const FAVICONS_QUERY: &str = "SELECT page_url.url as page_url, icon_info.url as icon_url, icon_info.uuid as uuid, timestamp, width, height FROM page_url JOIN icon_info ON page_url.uuid = icon_info.uuid";
+
+#[derive(Debug, Serialize)]
+pub struct SafariFavicons {
+    pub results: Vec<Favicon>,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Favicon {
+    pub page_url: String,
+    pub icon_url: String,
+    pub uuid: String,
+    pub timestamp: f64, // UNIX Epoch seconds
+    pub width: f64,
+    pub height: f64,
+    pub image: Option<Vec<u8>>, // Cached icon image, only set when exporting images
+}
+
+impl SafariFavicons {
+    /// Get Safari favicons SQLITE file for all users. Set `export_images` to include the cached icon images
+    pub fn get_users_favicons(export_images: bool) -> Result<Vec<SafariFavicons>, SafariError> {
+        let user_files = get_user_files("/Library/Safari/Favicon Cache/favicons.db")?;
+
+        let mut safari_favicons: Vec<SafariFavicons> = Vec::new();
+        for user_file in user_files {
+            let results = SafariFavicons::get_favicons(&user_file.path, export_images)?;
+            let favicons = SafariFavicons {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_favicons.push(favicons);
+        }
+        Ok(safari_favicons)
+    }
+
+    /// Query the page and icon URLs based on provided path. Set `export_images` to include the cached icon images
+    pub fn get_favicons(path: &str, export_images: bool) -> Result<Vec<Favicon>, SafariError> {
+        let conn = open_sqlite(path)?;
+        let statement = conn.prepare(FAVICONS_QUERY);
+        let mut stmt = match statement {
+            Ok(query) => query,
+            Err(err) => {
+                error!("Failed to compose Safari favicons SQL query {:?}", err);
+                return Err(SafariError::BadSQL);
+            }
+        };
+
+        let favicons_data = stmt.query_map([], |row| {
+            let timestamp: f64 = row.get("timestamp")?;
+            Ok(Favicon {
+                page_url: row.get("page_url")?,
+                icon_url: row.get("icon_url")?,
+                uuid: row.get("uuid")?,
+                timestamp: cocoa_to_unixepoch(timestamp),
+                width: row.get("width")?,
+                height: row.get("height")?,
+                image: None,
+            })
+        });
+
+        let image_directory = Path::new(path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join("favicons");
+        match favicons_data {
+            Ok(favicons_iter) => {
+                let mut favicons: Vec<Favicon> = Vec::new();
+                for favicon in favicons_iter {
+                    match favicon {
+                        Ok(mut favicon_data) => {
+                            if export_images {
+                                favicon_data.image =
+                                    SafariFavicons::get_image(&image_directory, &favicon_data.uuid);
+                            }
+                            favicons.push(favicon_data);
+                        }
+                        Err(err) => {
+                            warn!("Failed to iterate through Safari favicons data: {:?}", err);
+                        }
+                    }
+                }
+                Ok(favicons)
+            }
+            Err(err) => {
+                error!(
+                    "Failed to get Safari favicons data from SQLITE file: {:?}",
+                    err
+                );
+                Err(SafariError::SqliteParse)
+            }
+        }
+    }
+
+    // Read the cached icon image, if it exists. The UUID comes from the database and must not contain path components
+    fn get_image(image_directory: &Path, uuid: &str) -> Option<Vec<u8>> {
+        if uuid.is_empty()
+            || !uuid
+                .chars()
+                .all(|value| value.is_ascii_hexdigit() || value == '-')
+        {
+            warn!("Invalid favicon image UUID {}", uuid);
+            return None;
+        }
+        let image_path = image_directory.join(uuid);
+        if !image_path.is_file() {
+            return None;
+        }
+        match read(&image_path) {
+            Ok(results) => Some(results),
+            Err(err) => {
+                warn!(
+                    "Failed to read favicon image {}: {:?}",
+                    image_path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::SafariFavicons;
+
+    #[test]
+    fn test_get_favicons() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Favicon Cache/favicons.db");
+        let results =
+            SafariFavicons::get_favicons(&test_location.display().to_string(), false).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].page_url,
+            "https://github.com/PowerShell/PowerShell/releases/tag/v7.2.5"
+        );
+        assert_eq!(
+            results[0].icon_url,
+            "https://github.githubassets.com/favicons/favicon.png"
+        );
+        assert_eq!(results[0].uuid, "A1B2C3D4-0000-4000-8000-000000000001");
+        assert_eq!(results[0].timestamp, 1656266400.0);
+        assert_eq!(results[0].width, 32.0);
+        assert_eq!(results[0].height, 32.0);
+        assert_eq!(results[0].image, None);
+
+        assert_eq!(
+            results[1].page_url,
+            "https://pastebin.example.com/deleted-page"
+        );
+        assert_eq!(results[1].icon_url, results[0].icon_url);
+        assert_eq!(results[2].width, 16.0);
+    }
+
+    #[test]
+    fn test_get_favicons_export_images() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Favicon Cache/favicons.db");
+        let results =
+            SafariFavicons::get_favicons(&test_location.display().to_string(), true).unwrap();
+
+        assert_eq!(
+            results[0].image.as_deref(),
+            Some(&b"\x89PNG\r\n\x1a\nfavicon"[..])
+        );
+        // No cached image for this icon
+        assert_eq!(results[2].image, None);
+    }
+
+    #[test]
+    fn test_get_image_bad_uuid() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Favicon Cache/favicons");
+
+        assert!(
+            SafariFavicons::get_image(&test_location, "A1B2C3D4-0000-4000-8000-000000000001")
+                .is_some()
+        );
+        assert_eq!(
+            SafariFavicons::get_image(&test_location, "../favicons.db"),
+            None
+        );
+        assert_eq!(SafariFavicons::get_image(Path::new("/"), "etc/hosts"), None);
+    }
+}
@@ -4,13 +4,13 @@
 //!   Safari History
 //!   Safari Downloads
 
-use std::{fs::read_dir, path::Path};
+use log::{error, warn};
+use rusqlite::{params, Connection, Params};
+use serde::{Deserialize, Serialize};
 
-use log::{error, info, warn};
-use rusqlite::{Connection, OpenFlags};
-use serde::Serialize;
+use crate::{error::SafariError, sqlite::open_sqlite, users::get_user_files};
 
-use crate::error::SafariError;
+const HISTORY_QUERY: &str = "SELECT history_items.id as history_item_id, history_visits.id as visit_id, url, domain_expansion, visit_count, daily_visit_counts,weekly_visit_counts,autocomplete_triggers,should_recompute_derived_visit_counts,visit_count_score,status_code,visit_time,title,load_successful,http_non_get,synthesized,redirect_source,redirect_destination,origin,generation,attributes,score FROM history_items JOIN history_visits ON history_visits.history_item = history_items.id";
 
 #[derive(Debug, Serialize)]
 pub struct SafariHistory {
@@ -19,9 +19,10 @@ pub struct SafariHistory {
     pub user: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone)]
 pub struct History {
     pub id: i64,
+    pub visit_id: i64,
     pub url: String,
     pub domain_expansion: Option<String>, // Domain expansion entry, value is optional (Can be null)
     pub visit_count: i64,
@@ -33,79 +34,138 @@ pub struct History {
     pub status_code: i64,
     pub visit_time: f64,
     pub load_successful: bool,
-    pub title: Option<String>, // Title entry, value is optional (Can be null)
+    pub redirect_source: Option<i64>, // Visit ID that redirected to this visit (Can be null)
+    pub redirect_destination: Option<i64>, // Visit ID this visit redirected to (Can be null)
+    pub title: Option<String>,        // Title entry, value is optional (Can be null)
     pub attributes: f64,
     pub score: f64,
 }
 
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryTombstone {
+    pub id: i64,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub url: Option<String>, // Null when a whole time range was cleared
+    pub generation: i64,
+}
+
+/// Last visit seen in a History.db file. Persist between runs to only collect new visits
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HistoryCheckpoint {
+    pub path: String,
+    pub last_visit_id: i64,
+    pub last_visit_time: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IncrementalHistory {
+    pub history: SafariHistory,
+    pub checkpoint: HistoryCheckpoint,
+}
+
 impl SafariHistory {
     /// Get Safari SQLITE History file for all users to get browser history
     pub fn get_users_history() -> Result<Vec<SafariHistory>, SafariError> {
-        let base_directory = "/Users/";
-        let history_path = "/Library/Safari/History.db";
-        let users = read_dir(base_directory);
+        let user_files = get_user_files("/Library/Safari/History.db")?;
 
         let mut safari_history: Vec<SafariHistory> = Vec::new();
-        match users {
-            Ok(dir) => {
-                for entry in dir {
-                    match entry {
-                        Ok(entry_result) => {
-                            let path = format!("{}{}", entry_result.path().display(), history_path);
-                            let full_path = Path::new(&path);
-                            if !full_path.is_file() {
-                                continue;
-                            }
-                            info!("Parsing file path: {}", path);
-
-                            let results =
-                                SafariHistory::get_history(&full_path.display().to_string())?;
-
-                            let username = entry_result
-                                .path()
-                                .display()
-                                .to_string()
-                                .replace("/Users/", "");
-                            let history = SafariHistory {
-                                results,
-                                path,
-                                user: username,
-                            };
-
-                            safari_history.push(history);
-                        }
-                        Err(err) => warn!("Failed to get user directory: {:?}", err),
-                    }
-                }
-            }
-            Err(err) => {
-                error!(
-                    "Failed to read base directory {}: {:?}",
-                    base_directory, err
-                );
-                return Err(SafariError::Path);
-            }
+        for user_file in user_files {
+            let results = SafariHistory::get_history(&user_file.path)?;
+            let history = SafariHistory {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_history.push(history);
         }
         Ok(safari_history)
     }
 
     /// Query the URL history tables based on provided path
     pub fn get_history(path: &str) -> Result<Vec<History>, SafariError> {
-        // Bypass SQLITE file lock
-        let history_file = format!("file:{}?immutable=1", path);
-        let connection = Connection::open_with_flags(
-            history_file,
-            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+        let conn = open_sqlite(path)?;
+
+        let history_vec = SafariHistory::query_history(&conn, HISTORY_QUERY, [])?;
+        if history_vec.is_empty() {
+            return Err(SafariError::NoHistory);
+        }
+        Ok(history_vec)
+    }
+
+    /// Query only the visits newer than the provided checkpoint. Returns the new visits and the checkpoint to persist for the next run
+    pub fn get_history_since(
+        path: &str,
+        checkpoint: &HistoryCheckpoint,
+    ) -> Result<(Vec<History>, HistoryCheckpoint), SafariError> {
+        let conn = open_sqlite(path)?;
+
+        // Visit time is also checked in case the database was recreated and visit ids started over
+        let query = format!(
+            "{} WHERE history_visits.id > ?1 OR history_visits.visit_time > ?2 ORDER BY history_visits.id",
+            HISTORY_QUERY
         );
-        let conn = match connection {
-            Ok(connect) => connect,
-            Err(err) => {
-                error!("Failed to read Safari SQLITE history file {:?}", err);
-                return Err(SafariError::SqliteParse);
-            }
+        let history_vec = SafariHistory::query_history(
+            &conn,
+            &query,
+            params![checkpoint.last_visit_id, checkpoint.last_visit_time],
+        )?;
+
+        let mut new_checkpoint = HistoryCheckpoint {
+            path: path.to_string(),
+            last_visit_id: checkpoint.last_visit_id,
+            last_visit_time: checkpoint.last_visit_time,
         };
+        for history in &history_vec {
+            if history.visit_id > new_checkpoint.last_visit_id {
+                new_checkpoint.last_visit_id = history.visit_id;
+            }
+            if history.visit_time > new_checkpoint.last_visit_time {
+                new_checkpoint.last_visit_time = history.visit_time;
+            }
+        }
+        Ok((history_vec, new_checkpoint))
+    }
 
-        let  statement = conn.prepare("SELECT history_items.id as history_item_id, url, domain_expansion, visit_count, daily_visit_counts,weekly_visit_counts,autocomplete_triggers,should_recompute_derived_visit_counts,visit_count_score,status_code,visit_time,title,load_successful,http_non_get,synthesized,redirect_destination,origin,generation,attributes,score FROM history_items JOIN history_visits ON history_visits.history_item = history_items.id");
+    /// Get new Safari history for all users based on previously saved checkpoints. Databases without a checkpoint are parsed in full
+    pub fn get_users_history_since(
+        checkpoints: &[HistoryCheckpoint],
+    ) -> Result<Vec<IncrementalHistory>, SafariError> {
+        let user_files = get_user_files("/Library/Safari/History.db")?;
+
+        let mut safari_history: Vec<IncrementalHistory> = Vec::new();
+        for user_file in user_files {
+            let default_checkpoint = HistoryCheckpoint {
+                path: user_file.path.clone(),
+                ..Default::default()
+            };
+            let checkpoint = checkpoints
+                .iter()
+                .find(|checkpoint| checkpoint.path == user_file.path)
+                .unwrap_or(&default_checkpoint);
+
+            let (results, checkpoint) =
+                SafariHistory::get_history_since(&user_file.path, checkpoint)?;
+            let history = IncrementalHistory {
+                history: SafariHistory {
+                    results,
+                    path: user_file.path,
+                    user: user_file.user,
+                },
+                checkpoint,
+            };
+            safari_history.push(history);
+        }
+        Ok(safari_history)
+    }
+
+    /// Run a history query and collect the rows
+    fn query_history<P: Params>(
+        conn: &Connection,
+        query: &str,
+        params: P,
+    ) -> Result<Vec<History>, SafariError> {
+        let statement = conn.prepare(query);
         let mut stmt = match statement {
             Ok(query) => query,
             Err(err) => {
@@ -115,9 +175,10 @@ impl SafariHistory {
         };
 
         // Get browser history data
-        let history_data = stmt.query_map([], |row| {
+        let history_data = stmt.query_map(params, |row| {
             Ok(History {
                 id: row.get("history_item_id")?,
+                visit_id: row.get("visit_id")?,
                 url: row.get("url")?,
                 title: row.get("title")?,
                 visit_count: row.get("visit_count")?,
@@ -131,6 +192,8 @@ impl SafariHistory {
                 status_code: row.get("status_code")?,
                 visit_time: row.get("visit_time")?,
                 load_successful: row.get("load_successful")?,
+                redirect_source: row.get("redirect_source")?,
+                redirect_destination: row.get("redirect_destination")?,
                 attributes: row.get("attributes")?,
                 score: row.get("score")?,
             })
@@ -148,9 +211,6 @@ impl SafariHistory {
                         }
                     }
                 }
-                if history_vec.is_empty() {
-                    return Err(SafariError::NoHistory);
-                }
                 Ok(history_vec)
             }
             Err(err) => {
@@ -162,13 +222,61 @@ impl SafariHistory {
             }
         }
     }
+
+    /// Query the history tombstones table based on provided path. Tombstones are left behind when history is cleared
+    pub fn get_tombstones(path: &str) -> Result<Vec<HistoryTombstone>, SafariError> {
+        let conn = open_sqlite(path)?;
+
+        let statement = conn
+            .prepare("SELECT id, start_time, end_time, url, generation FROM history_tombstones");
+        let mut stmt = match statement {
+            Ok(query) => query,
+            Err(err) => {
+                error!("Failed to compose Safari tombstone SQL query {:?}", err);
+                return Err(SafariError::BadSQL);
+            }
+        };
+
+        let tombstone_data = stmt.query_map([], |row| {
+            Ok(HistoryTombstone {
+                id: row.get("id")?,
+                start_time: row.get("start_time")?,
+                end_time: row.get("end_time")?,
+                url: row.get("url")?,
+                generation: row.get("generation")?,
+            })
+        });
+
+        match tombstone_data {
+            Ok(tombstone_iter) => {
+                let mut tombstone_vec: Vec<HistoryTombstone> = Vec::new();
+
+                for tombstone in tombstone_iter {
+                    match tombstone {
+                        Ok(tombstone_data) => tombstone_vec.push(tombstone_data),
+                        Err(err) => {
+                            warn!("Failed to iterate through Safari tombstone data: {:?}", err);
+                        }
+                    }
+                }
+                Ok(tombstone_vec)
+            }
+            Err(err) => {
+                error!(
+                    "Failed to get Safari tombstone data from SQLITE file: {:?}",
+                    err
+                );
+                Err(SafariError::SqliteParse)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
-    use super::SafariHistory;
+    use super::{HistoryCheckpoint, SafariHistory};
 
     #[test]
     #[ignore = "Get live users Safari history"]
@@ -185,6 +293,7 @@ mod tests {
 
         assert_eq!(history.len(), 42);
         assert_eq!(history[0].id, 167);
+        assert_eq!(history[0].visit_id, 244);
         assert_eq!(
             history[0].url,
             "https://www.google.com/search?client=safari&rls=en&q=duckduckgo&ie=UTF-8&oe=UTF-8"
@@ -203,6 +312,8 @@ mod tests {
         assert_eq!(history[0].status_code, 0);
         assert_eq!(history[0].visit_time, 677386043.546784);
         assert_eq!(history[0].load_successful, true);
+        assert_eq!(history[0].redirect_source, None);
+        assert_eq!(history[0].redirect_destination, None);
         assert_eq!(
             history[0].title.as_ref().unwrap(),
             "duckduckgo - Google Search"
@@ -236,4 +347,49 @@ mod tests {
         assert_eq!(history[9].attributes, 0.0);
         assert_eq!(history[9].score, 100.0);
     }
+
+    #[test]
+    fn test_safari_tombstones() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/History.db");
+        let tombstones =
+            SafariHistory::get_tombstones(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(tombstones.len(), 18);
+        assert_eq!(tombstones[0].id, 1);
+        assert_eq!(tombstones[0].start_time, -63114076800.0);
+        assert_eq!(tombstones[0].end_time, 616875400.580184);
+        assert_eq!(tombstones[0].url, None);
+        assert_eq!(tombstones[0].generation, 0);
+    }
+
+    #[test]
+    fn test_safari_history_since() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/History.db");
+        let path = test_location.display().to_string();
+
+        let (history, checkpoint) =
+            SafariHistory::get_history_since(&path, &HistoryCheckpoint::default()).unwrap();
+        assert_eq!(history.len(), 42);
+        assert_eq!(checkpoint.path, path);
+        assert_eq!(checkpoint.last_visit_id, 285);
+        assert_eq!(checkpoint.last_visit_time, 677962607.92499);
+
+        let previous = HistoryCheckpoint {
+            path: path.clone(),
+            last_visit_id: 280,
+            last_visit_time: 677958203.380812,
+        };
+        let (history, checkpoint) = SafariHistory::get_history_since(&path, &previous).unwrap();
+        assert_eq!(history.len(), 5);
+        assert_eq!(history[0].visit_id, 281);
+        assert_eq!(checkpoint.last_visit_id, 285);
+
+        let (history, next_checkpoint) =
+            SafariHistory::get_history_since(&path, &checkpoint).unwrap();
+        assert!(history.is_empty());
+        assert_eq!(next_checkpoint.last_visit_id, checkpoint.last_visit_id);
+        assert_eq!(next_checkpoint.last_visit_time, checkpoint.last_visit_time);
+    }
 }
@@ -0,0 +1,202 @@
+//! Compare two Safari History snapshots
+//!
+//! Added and removed entries are keyed by history item id and visit id. Modified entries are keyed by history item id,
+//! since `visit_count` belongs to the history item. Tombstones are keyed by their id
+
+use std::collections::{HashMap, HashSet};
+
+use log::warn;
+use serde::Serialize;
+
+use crate::{
+    error::SafariError,
+    history::{History, HistoryTombstone, SafariHistory},
+};
+
+#[derive(Debug, Serialize)]
+pub struct HistoryDiff {
+    pub added: Vec<History>,
+    pub removed: Vec<History>,
+    pub modified: Vec<ModifiedHistory>,
+    pub new_tombstones: Vec<HistoryTombstone>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModifiedHistory {
+    pub id: i64,
+    pub url: String,
+    pub previous_visit_count: i64,
+    pub current_visit_count: i64,
+}
+
+impl HistoryDiff {
+    /// Compare two History.db snapshots on disk, including their tombstones
+    pub fn compare_files(
+        previous_path: &str,
+        current_path: &str,
+    ) -> Result<HistoryDiff, SafariError> {
+        let previous = HistoryDiff::get_snapshot_history(previous_path)?;
+        let current = HistoryDiff::get_snapshot_history(current_path)?;
+
+        let mut diff = HistoryDiff::compare(&previous, &current);
+        diff.new_tombstones = HistoryDiff::compare_tombstones(
+            &SafariHistory::get_tombstones(previous_path)?,
+            &SafariHistory::get_tombstones(current_path)?,
+        );
+        Ok(diff)
+    }
+
+    /// Compare two `get_history` results. Tombstones are not part of the history results, see `compare_tombstones`
+    pub fn compare(previous: &[History], current: &[History]) -> HistoryDiff {
+        let previous_keys: HashSet<(i64, i64)> = previous
+            .iter()
+            .map(|history| (history.id, history.visit_id))
+            .collect();
+        let current_keys: HashSet<(i64, i64)> = current
+            .iter()
+            .map(|history| (history.id, history.visit_id))
+            .collect();
+
+        let mut diff = HistoryDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            modified: Vec::new(),
+            new_tombstones: Vec::new(),
+        };
+
+        for history in current {
+            if !previous_keys.contains(&(history.id, history.visit_id)) {
+                diff.added.push(history.clone());
+            }
+        }
+
+        // Every visit row repeats its item's visit_count, only report each item once
+        let previous_counts: HashMap<i64, i64> = previous
+            .iter()
+            .map(|history| (history.id, history.visit_count))
+            .collect();
+        let mut seen_items: HashSet<i64> = HashSet::new();
+        for history in current {
+            if !seen_items.insert(history.id) {
+                continue;
+            }
+            let previous_visit_count = match previous_counts.get(&history.id) {
+                Some(count) => *count,
+                None => continue,
+            };
+            if previous_visit_count == history.visit_count {
+                continue;
+            }
+            let modified = ModifiedHistory {
+                id: history.id,
+                url: history.url.clone(),
+                previous_visit_count,
+                current_visit_count: history.visit_count,
+            };
+            diff.modified.push(modified);
+        }
+
+        for history in previous {
+            if !current_keys.contains(&(history.id, history.visit_id)) {
+                diff.removed.push(history.clone());
+            }
+        }
+        diff
+    }
+
+    /// Get tombstones in the current snapshot that were not in the previous snapshot
+    pub fn compare_tombstones(
+        previous: &[HistoryTombstone],
+        current: &[HistoryTombstone],
+    ) -> Vec<HistoryTombstone> {
+        let previous_ids: HashSet<i64> = previous.iter().map(|tombstone| tombstone.id).collect();
+        current
+            .iter()
+            .filter(|tombstone| !previous_ids.contains(&tombstone.id))
+            .cloned()
+            .collect()
+    }
+
+    // A snapshot taken after all history was cleared is still a valid snapshot
+    fn get_snapshot_history(path: &str) -> Result<Vec<History>, SafariError> {
+        match SafariHistory::get_history(path) {
+            Ok(results) => Ok(results),
+            Err(SafariError::NoHistory) => {
+                warn!("No history data in snapshot {}", path);
+                Ok(Vec::new())
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::HistoryDiff;
+    use crate::history::SafariHistory;
+
+    fn get_test_path() -> String {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/History.db");
+        test_location.display().to_string()
+    }
+
+    #[test]
+    fn test_compare() {
+        let previous = SafariHistory::get_history(&get_test_path()).unwrap();
+        let mut current = SafariHistory::get_history(&get_test_path()).unwrap();
+
+        let removed = current.remove(0);
+
+        // A revisit adds a visit row and bumps visit_count on every row of the item
+        let mut revisit = current
+            .iter()
+            .find(|history| history.id == 170)
+            .unwrap()
+            .clone();
+        revisit.visit_id = 1000;
+        revisit.visit_count += 1;
+        for history in current.iter_mut().filter(|history| history.id == 170) {
+            history.visit_count += 1;
+        }
+        current.push(revisit);
+        assert!(current.iter().filter(|history| history.id == 170).count() == 3);
+
+        let diff = HistoryDiff::compare(&previous, &current);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].visit_id, removed.visit_id);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].visit_id, 1000);
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].id, 170);
+        assert_eq!(
+            diff.modified[0].current_visit_count,
+            diff.modified[0].previous_visit_count + 1
+        );
+        assert!(diff.new_tombstones.is_empty());
+    }
+
+    #[test]
+    fn test_compare_tombstones() {
+        let current = SafariHistory::get_tombstones(&get_test_path()).unwrap();
+        let previous = &current[0..10];
+
+        let results = HistoryDiff::compare_tombstones(previous, &current);
+        assert_eq!(results.len(), 8);
+        assert_eq!(results[0].id, 11);
+    }
+
+    #[test]
+    fn test_compare_files() {
+        let diff = HistoryDiff::compare_files(&get_test_path(), &get_test_path()).unwrap();
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.modified.is_empty());
+        assert!(diff.new_tombstones.is_empty());
+
+        let serde_data = serde_json::to_string(&diff).unwrap();
+        assert!(serde_data.starts_with("{\"added\":[]"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Parse the Safari HSTS PLIST file
+//!
+//! Contains the hosts that sent a Strict-Transport-Security header, with the time the entry was created.
+//! Entries are not removed when History is cleared, so they can show sites were visited even if History.db is empty
+
+use std::collections::HashSet;
+
+use log::warn;
+use plist::Value;
+use serde::Serialize;
+
+use crate::{
+    error::SafariError,
+    history::History,
+    plist_values::{get_bool, open_plist},
+    time::cocoa_to_unixepoch,
+    url::get_host,
+    users::get_user_files,
+};
+
+#[derive(Debug, Serialize)]
+pub struct SafariHsts {
+    pub results: Vec<HstsEntry>,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HstsEntry {
+    pub host: String,
+    pub created: Option<f64>, // UNIX Epoch seconds, None for preloaded entries
+    pub expiry: Option<f64>,  // UNIX Epoch seconds, None if the entry never expires
+    pub include_subdomains: bool,
+    pub storage_session: String, // CFNetwork storage session containing the entry
+}
+
+impl SafariHsts {
+    /// Get Safari HSTS PLIST file for all users
+    pub fn get_users_hsts() -> Result<Vec<SafariHsts>, SafariError> {
+        let user_files = get_user_files("/Library/Cookies/HSTS.plist")?;
+
+        let mut safari_hsts: Vec<SafariHsts> = Vec::new();
+        for user_file in user_files {
+            let results = SafariHsts::get_hsts(&user_file.path)?;
+            let hsts = SafariHsts {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_hsts.push(hsts);
+        }
+        Ok(safari_hsts)
+    }
+
+    /// Parse Safari HSTS PLIST file at provided path
+    pub fn get_hsts(path: &str) -> Result<Vec<HstsEntry>, SafariError> {
+        let hsts_plist = open_plist(path)?;
+
+        let mut entries: Vec<HstsEntry> = Vec::new();
+        // Entries are grouped by storage session, other root keys are metadata such as the content version
+        for (storage_session, hosts) in hsts_plist.iter() {
+            let hosts_dict = match hosts.as_dictionary() {
+                Some(results) => results,
+                None => continue,
+            };
+
+            for (host, entry) in hosts_dict.iter() {
+                let entry_dict = match entry.as_dictionary() {
+                    Some(results) => results,
+                    None => {
+                        warn!("HSTS entry for {} is not a dictionary", host);
+                        continue;
+                    }
+                };
+
+                entries.push(HstsEntry {
+                    host: host.to_string(),
+                    created: get_cocoa_time(entry_dict.get("Create Time")),
+                    expiry: get_cocoa_time(entry_dict.get("Expiry")),
+                    include_subdomains: get_bool(entry_dict, "Include Subdomains").unwrap_or(false),
+                    storage_session: storage_session.to_string(),
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Get the HSTS entries created by a visit that have no matching host in the provided History.
+    /// Preloaded entries (without a creation time) are skipped
+    pub fn missing_from_history(entries: &[HstsEntry], history: &[History]) -> Vec<HstsEntry> {
+        let history_hosts: HashSet<String> = history
+            .iter()
+            .filter_map(|visit| get_host(&visit.url))
+            .collect();
+
+        entries
+            .iter()
+            .filter(|entry| entry.created.is_some())
+            .filter(|entry| {
+                let host = entry.host.to_lowercase();
+                let subdomain = format!(".{}", host);
+                !history_hosts.iter().any(|history_host| {
+                    *history_host == host
+                        || (entry.include_subdomains && history_host.ends_with(&subdomain))
+                })
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+// HSTS times are Cocoa timestamp numbers. Entries that never expire use infinity
+fn get_cocoa_time(value: Option<&Value>) -> Option<f64> {
+    let timestamp = value?.as_real()?;
+    if !timestamp.is_finite() {
+        return None;
+    }
+    Some(cocoa_to_unixepoch(timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::SafariHsts;
+    use crate::history::SafariHistory;
+
+    #[test]
+    fn test_get_hsts() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/HSTS.plist");
+        let results = SafariHsts::get_hsts(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].host, "github.com");
+        assert_eq!(results[0].created, Some(1656266400.25));
+        assert_eq!(results[0].expiry, Some(1687802400.0));
+        assert!(results[0].include_subdomains);
+        assert_eq!(
+            results[0].storage_session,
+            "com.apple.CFNetwork.defaultStorageSession"
+        );
+
+        assert_eq!(results[1].host, "pastebin.example.com");
+        assert!(!results[1].include_subdomains);
+
+        // Preloaded entry that never expires
+        assert_eq!(results[2].host, "preloaded.example.org");
+        assert_eq!(results[2].created, None);
+        assert_eq!(results[2].expiry, None);
+    }
+
+    #[test]
+    fn test_missing_from_history() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/HSTS.plist");
+        let entries = SafariHsts::get_hsts(&test_location.display().to_string()).unwrap();
+
+        test_location.pop();
+        test_location.push("History.db");
+        let history = SafariHistory::get_history(&test_location.display().to_string()).unwrap();
+
+        let missing = SafariHsts::missing_from_history(&entries, &history);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].host, "pastebin.example.com");
+
+        // Every visited host is missing from an empty History
+        let missing = SafariHsts::missing_from_history(&entries, &[]);
+        assert_eq!(missing.len(), 3);
+    }
+}
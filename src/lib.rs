@@ -1,5 +1,29 @@
+pub mod bookmarks;
+pub mod cloud_tabs;
+pub mod cookies;
+pub mod correlation;
+pub mod download_file;
+pub mod download_risk;
+pub mod download_url;
+pub mod download_volume;
 pub mod downloads;
-mod downloads_plist;
+pub mod downloads_plist;
 pub mod error;
+pub mod favicons;
 pub mod history;
+pub mod history_diff;
+pub mod hsts;
+mod plist_values;
+pub mod quarantine;
+pub mod reading_list;
+pub mod recently_closed;
+pub mod safari_tabs;
+pub mod sandbox;
+pub mod session;
 mod size;
+mod sqlite;
+mod time;
+pub mod timeline;
+pub mod top_sites;
+mod url;
+mod users;
@@ -0,0 +1,119 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::error;
+use plist::{Dictionary, Value};
+
+use crate::error::SafariError;
+
+// Open a PLIST file that has a dictionary at the root
+pub(crate) fn open_plist(path: &str) -> Result<Dictionary, SafariError> {
+    let plist_results: Result<Dictionary, plist::Error> = plist::from_file(path);
+    match plist_results {
+        Ok(results) => Ok(results),
+        Err(err) => {
+            error!("Failed to parse PLIST file at {}: {:?}", path, err);
+            Err(SafariError::Plist)
+        }
+    }
+}
+
+// Get a string value from a dictionary
+pub(crate) fn get_string(dict: &Dictionary, key: &str) -> Option<String> {
+    dict.get(key)?.as_string().map(str::to_string)
+}
+
+// Get an integer value from a dictionary
+pub(crate) fn get_int(dict: &Dictionary, key: &str) -> Option<i64> {
+    dict.get(key)?.as_signed_integer()
+}
+
+// Get a bool value from a dictionary
+pub(crate) fn get_bool(dict: &Dictionary, key: &str) -> Option<bool> {
+    dict.get(key)?.as_boolean()
+}
+
+// Get a data value from a dictionary
+pub(crate) fn get_data(dict: &Dictionary, key: &str) -> Option<Vec<u8>> {
+    dict.get(key)?.as_data().map(<[u8]>::to_vec)
+}
+
+// Get a date value from a dictionary as UNIX Epoch seconds
+pub(crate) fn get_date(dict: &Dictionary, key: &str) -> Option<f64> {
+    get_date_value(dict.get(key)?)
+}
+
+// Convert a date value to UNIX Epoch seconds. Sub-second precision is kept and timestamps before 1970 are negative
+pub(crate) fn get_date_value(value: &Value) -> Option<f64> {
+    let date: SystemTime = value.as_date()?.into();
+    match date.duration_since(UNIX_EPOCH) {
+        Ok(results) => Some(results.as_secs_f64()),
+        Err(err) => Some(-err.duration().as_secs_f64()),
+    }
+}
+
+// Get a dictionary value from a dictionary
+pub(crate) fn get_dictionary<'a>(dict: &'a Dictionary, key: &str) -> Option<&'a Dictionary> {
+    dict.get(key)?.as_dictionary()
+}
+
+// Get an array value from a dictionary
+pub(crate) fn get_array<'a>(dict: &'a Dictionary, key: &str) -> Option<&'a Vec<Value>> {
+    dict.get(key)?.as_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use plist::{Dictionary, Value};
+
+    use super::{
+        get_array, get_bool, get_data, get_date, get_dictionary, get_int, get_string, open_plist,
+    };
+
+    #[test]
+    fn test_get_values() {
+        let mut dict = Dictionary::new();
+        dict.insert(String::from("string"), Value::String(String::from("test")));
+        dict.insert(String::from("int"), Value::Integer(10.into()));
+        dict.insert(String::from("bool"), Value::Boolean(true));
+        dict.insert(String::from("data"), Value::Data(vec![1, 2]));
+        dict.insert(String::from("dict"), Value::Dictionary(Dictionary::new()));
+        dict.insert(
+            String::from("date"),
+            Value::Date((UNIX_EPOCH + Duration::from_millis(1656266417500)).into()),
+        );
+        dict.insert(
+            String::from("old_date"),
+            Value::Date((UNIX_EPOCH - Duration::from_secs(86400)).into()),
+        );
+        dict.insert(
+            String::from("array"),
+            Value::Array(vec![Value::Boolean(true)]),
+        );
+
+        assert_eq!(get_string(&dict, "string").unwrap(), "test");
+        assert_eq!(get_int(&dict, "int"), Some(10));
+        assert_eq!(get_bool(&dict, "bool"), Some(true));
+        assert_eq!(get_data(&dict, "data"), Some(vec![1, 2]));
+        assert!(get_dictionary(&dict, "dict").unwrap().is_empty());
+        assert_eq!(get_array(&dict, "array").unwrap().len(), 1);
+        assert_eq!(get_date(&dict, "date"), Some(1656266417.5));
+        assert_eq!(get_date(&dict, "old_date"), Some(-86400.0));
+
+        // Missing keys and wrong types
+        assert_eq!(get_string(&dict, "missing"), None);
+        assert_eq!(get_data(&dict, "string"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Plist")]
+    fn test_open_plist_bad_file() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/badfile.txt");
+        let _ = open_plist(&test_location.display().to_string()).unwrap();
+    }
+}
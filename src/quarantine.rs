@@ -0,0 +1,312 @@
+//! Parse the macOS QuarantineEventsV2 SQLITE file
+//!
+//! LaunchServices records a quarantine event for files downloaded by quarantine aware applications (such as Safari).
+//! The events include the page the download was started from, which Downloads.plist does not track
+
+use log::{error, warn};
+use serde::Serialize;
+
+use crate::{
+    downloads::Downloads, error::SafariError, sqlite::open_sqlite, time::cocoa_to_unixepoch,
+    users::get_user_files,
+};
+
+const QUARANTINE_QUERY: &str = "SELECT LSQuarantineEventIdentifier, LSQuarantineTimeStamp, LSQuarantineAgentBundleIdentifier, LSQuarantineAgentName, LSQuarantineDataURLString, LSQuarantineSenderName, LSQuarantineSenderAddress, LSQuarantineTypeNumber, LSQuarantineOriginTitle, LSQuarantineOriginURLString, LSQuarantineOriginAlias FROM LSQuarantineEvent";
+
+#[derive(Debug, Serialize)]
+pub struct SafariQuarantine {
+    pub results: Vec<QuarantineEvent>,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct QuarantineEvent {
+    pub event_id: String,
+    pub timestamp: Option<f64>, // UNIX Epoch seconds (Can be null)
+    pub agent_bundle_id: Option<String>,
+    pub agent_name: Option<String>,
+    pub data_url: Option<String>, // URL the file was downloaded from
+    pub sender_name: Option<String>,
+    pub sender_address: Option<String>,
+    pub type_number: Option<i64>,
+    pub origin_title: Option<String>,
+    pub origin_url: Option<String>, // Page the download was started from
+    pub origin_alias: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuarantineDownload {
+    pub download_id: String,
+    pub source_url: String,
+    pub event_id: Option<String>, // Matching quarantine event, if any
+    pub origin_url: Option<String>,
+    pub origin_title: Option<String>,
+    pub agent_bundle_id: Option<String>,
+    pub seconds_difference: Option<f64>, // Seconds between the download and the quarantine event
+}
+
+impl SafariQuarantine {
+    /// Get the QuarantineEventsV2 SQLITE file for all users
+    pub fn get_users_quarantine() -> Result<Vec<SafariQuarantine>, SafariError> {
+        let user_files =
+            get_user_files("/Library/Preferences/com.apple.LaunchServices.QuarantineEventsV2")?;
+
+        let mut safari_quarantine: Vec<SafariQuarantine> = Vec::new();
+        for user_file in user_files {
+            let results = SafariQuarantine::get_quarantine(&user_file.path)?;
+            let quarantine = SafariQuarantine {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_quarantine.push(quarantine);
+        }
+        Ok(safari_quarantine)
+    }
+
+    /// Query the quarantine events table based on provided path
+    pub fn get_quarantine(path: &str) -> Result<Vec<QuarantineEvent>, SafariError> {
+        let conn = open_sqlite(path)?;
+
+        let statement = conn.prepare(QUARANTINE_QUERY);
+        let mut stmt = match statement {
+            Ok(query) => query,
+            Err(err) => {
+                error!("Failed to compose quarantine SQL query {:?}", err);
+                return Err(SafariError::BadSQL);
+            }
+        };
+
+        let quarantine_data = stmt.query_map([], |row| {
+            let timestamp: Option<f64> = row.get("LSQuarantineTimeStamp")?;
+            Ok(QuarantineEvent {
+                event_id: row.get("LSQuarantineEventIdentifier")?,
+                timestamp: timestamp.map(cocoa_to_unixepoch),
+                agent_bundle_id: row.get("LSQuarantineAgentBundleIdentifier")?,
+                agent_name: row.get("LSQuarantineAgentName")?,
+                data_url: row.get("LSQuarantineDataURLString")?,
+                sender_name: row.get("LSQuarantineSenderName")?,
+                sender_address: row.get("LSQuarantineSenderAddress")?,
+                type_number: row.get("LSQuarantineTypeNumber")?,
+                origin_title: row.get("LSQuarantineOriginTitle")?,
+                origin_url: row.get("LSQuarantineOriginURLString")?,
+                origin_alias: row.get("LSQuarantineOriginAlias")?,
+            })
+        });
+
+        match quarantine_data {
+            Ok(quarantine_iter) => {
+                let mut quarantine_vec: Vec<QuarantineEvent> = Vec::new();
+
+                for quarantine in quarantine_iter {
+                    match quarantine {
+                        Ok(quarantine_event) => quarantine_vec.push(quarantine_event),
+                        Err(err) => {
+                            warn!("Failed to iterate through quarantine data: {:?}", err);
+                        }
+                    }
+                }
+                Ok(quarantine_vec)
+            }
+            Err(err) => {
+                error!("Failed to get quarantine data from SQLITE file: {:?}", err);
+                Err(SafariError::SqliteParse)
+            }
+        }
+    }
+
+    /// Match each download to the Safari quarantine event with the same URL closest in time, within `window_seconds`.
+    /// Events without a timestamp are not matched and each event is matched to at most one download.
+    /// Downloads and quarantine events should come from the same user
+    pub fn match_downloads(
+        downloads: &[Downloads],
+        events: &[QuarantineEvent],
+        window_seconds: f64,
+    ) -> Vec<QuarantineDownload> {
+        // All possible download and event pairs, closest pairs are matched first
+        let mut candidates: Vec<(usize, usize, f64)> = Vec::new();
+        for (download_index, download) in downloads.iter().enumerate() {
+            for (event_index, event) in events.iter().enumerate() {
+                if !SafariQuarantine::is_safari_event(event)
+                    || event.data_url.as_deref() != Some(download.source_url.as_str())
+                {
+                    continue;
+                }
+
+                // Cannot tell which download an event belongs to without both timestamps
+                let seconds_difference =
+                    match SafariQuarantine::get_time_difference(download, event) {
+                        Some(results) => results,
+                        None => continue,
+                    };
+                if seconds_difference > window_seconds {
+                    continue;
+                }
+                candidates.push((download_index, event_index, seconds_difference));
+            }
+        }
+        candidates.sort_by(|first, second| first.2.total_cmp(&second.2));
+
+        let mut matched_events: Vec<Option<(usize, f64)>> = vec![None; downloads.len()];
+        let mut used_events: Vec<bool> = vec![false; events.len()];
+        for (download_index, event_index, seconds_difference) in candidates {
+            if matched_events[download_index].is_some() || used_events[event_index] {
+                continue;
+            }
+            matched_events[download_index] = Some((event_index, seconds_difference));
+            used_events[event_index] = true;
+        }
+
+        let mut quarantine_downloads: Vec<QuarantineDownload> = Vec::new();
+        for (download, matched_event) in downloads.iter().zip(matched_events) {
+            let mut quarantine_download = QuarantineDownload {
+                download_id: download.download_id.clone(),
+                source_url: download.source_url.clone(),
+                event_id: None,
+                origin_url: None,
+                origin_title: None,
+                agent_bundle_id: None,
+                seconds_difference: None,
+            };
+            if let Some((event_index, seconds_difference)) = matched_event {
+                let event = &events[event_index];
+                quarantine_download.event_id = Some(event.event_id.clone());
+                quarantine_download.origin_url = event.origin_url.clone();
+                quarantine_download.origin_title = event.origin_title.clone();
+                quarantine_download.agent_bundle_id = event.agent_bundle_id.clone();
+                quarantine_download.seconds_difference = Some(seconds_difference);
+            }
+            quarantine_downloads.push(quarantine_download);
+        }
+        quarantine_downloads
+    }
+
+    // Other browsers record quarantine events for the same URLs. Includes Safari Technology Preview
+    fn is_safari_event(event: &QuarantineEvent) -> bool {
+        match &event.agent_bundle_id {
+            Some(bundle_id) => bundle_id.starts_with("com.apple.Safari"),
+            None => false,
+        }
+    }
+
+    // Seconds between the quarantine event and the download start to finish range. None if either time is missing
+    fn get_time_difference(download: &Downloads, event: &QuarantineEvent) -> Option<f64> {
+        let timestamp = event.timestamp?;
+        let start = download
+            .download_entry_date
+            .or(download.download_entry_finish)?;
+        let finish = download.download_entry_finish.unwrap_or(start);
+
+        if timestamp < start {
+            return Some(start - timestamp);
+        }
+        if timestamp > finish {
+            return Some(timestamp - finish);
+        }
+        Some(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::SafariQuarantine;
+    use crate::downloads::SafariDownloads;
+
+    #[test]
+    fn test_get_quarantine() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/QuarantineEventsV2");
+        let results =
+            SafariQuarantine::get_quarantine(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].event_id, "5D6A2E4B-7A38-4F4B-9E2C-3B1F2C9D8E01");
+        assert!((results[0].timestamp.unwrap() - 1656266422.3).abs() < 0.001);
+        assert_eq!(
+            results[0].agent_bundle_id.as_deref(),
+            Some("com.apple.Safari")
+        );
+        assert_eq!(
+            results[0].origin_url.as_deref(),
+            Some("https://github.com/PowerShell/PowerShell/releases/download/v7.2.5/powershell-7.2.5-osx-arm64.pkg")
+        );
+        assert_eq!(results[2].agent_name.as_deref(), Some("Google Chrome"));
+        assert_eq!(results[2].origin_alias, None);
+    }
+
+    #[test]
+    fn test_match_downloads() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/QuarantineEventsV2");
+        let events =
+            SafariQuarantine::get_quarantine(&test_location.display().to_string()).unwrap();
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+
+        let results = SafariQuarantine::match_downloads(&downloads, &events, 60.0);
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].event_id.as_deref(),
+            Some("5D6A2E4B-7A38-4F4B-9E2C-3B1F2C9D8E01")
+        );
+        assert!(results[0].seconds_difference.unwrap() < 1.0);
+
+        // Same URL as the third download, but the quarantine event is from the earlier download
+        assert_eq!(results[1].event_id, None);
+        assert_eq!(results[1].origin_url, None);
+
+        assert_eq!(
+            results[2].event_id.as_deref(),
+            Some("A1C3E5F7-0B2D-4F6A-8C1E-3D5F7A9B1C23")
+        );
+        assert_eq!(
+            results[2].origin_url.as_deref(),
+            Some("https://github.com/PowerShell/PowerShell/releases/download/v7.2.5/powershell-7.2.5-osx-x64.pkg")
+        );
+    }
+
+    #[test]
+    fn test_match_downloads_safari_events_once() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/QuarantineEventsV2");
+        let events =
+            SafariQuarantine::get_quarantine(&test_location.display().to_string()).unwrap();
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let mut downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+        downloads.truncate(1);
+        // Same download listed twice, the event can only be matched once
+        downloads.append(
+            &mut SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap(),
+        );
+
+        let results = SafariQuarantine::match_downloads(&downloads, &events, 60.0);
+        assert_eq!(
+            results[0].event_id.as_deref(),
+            Some("5D6A2E4B-7A38-4F4B-9E2C-3B1F2C9D8E01")
+        );
+        assert_eq!(results[1].event_id, None);
+
+        // Events from other browsers or without a timestamp are not matched
+        let mut chrome_event = events[0].clone();
+        chrome_event.agent_bundle_id = Some(String::from("com.google.Chrome"));
+        let mut untimed_event = events[0].clone();
+        untimed_event.timestamp = None;
+
+        let results = SafariQuarantine::match_downloads(
+            &downloads[0..1],
+            &[chrome_event, untimed_event],
+            60.0,
+        );
+        assert_eq!(results[0].event_id, None);
+        assert_eq!(results[0].seconds_difference, None);
+    }
+}
@@ -0,0 +1,168 @@
+//! Parse the Safari Reading List
+//!
+//! Reading List items are stored in the Bookmarks PLIST file under the `com.apple.ReadingList` folder.
+//! Items saved for offline reading have a web archive in the `ReadingListArchives` directory next to the Bookmarks PLIST file
+
+use std::path::Path;
+
+use log::warn;
+use plist::Dictionary;
+use serde::Serialize;
+
+use crate::{
+    bookmarks::READING_LIST_TITLE,
+    error::SafariError,
+    plist_values::{get_array, get_date, get_dictionary, get_string, open_plist},
+    users::get_user_files,
+};
+
+#[derive(Debug, Serialize)]
+pub struct SafariReadingList {
+    pub results: Vec<ReadingListItem>,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadingListItem {
+    pub uuid: String,
+    pub title: String,
+    pub url: String,
+    pub date_added: Option<f64>,        // UNIX Epoch seconds
+    pub date_last_viewed: Option<f64>,  // UNIX Epoch seconds, None if never viewed
+    pub date_last_fetched: Option<f64>, // UNIX Epoch seconds
+    pub preview_text: Option<String>,
+    pub source_bundle_id: Option<String>, // App that added the item
+    pub source_app_name: Option<String>,
+    pub site_name: Option<String>,
+    pub image_url: Option<String>,
+    pub archive_path: Option<String>, // Offline copy of the page, None if it does not exist on disk
+}
+
+impl SafariReadingList {
+    /// Get the Safari Reading List for all users
+    pub fn get_users_reading_list() -> Result<Vec<SafariReadingList>, SafariError> {
+        let user_files = get_user_files("/Library/Safari/Bookmarks.plist")?;
+
+        let mut safari_reading_list: Vec<SafariReadingList> = Vec::new();
+        for user_file in user_files {
+            let results = SafariReadingList::get_reading_list(&user_file.path)?;
+            let reading_list = SafariReadingList {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_reading_list.push(reading_list);
+        }
+        Ok(safari_reading_list)
+    }
+
+    /// Get the Reading List items from the Safari Bookmarks PLIST file at provided path
+    pub fn get_reading_list(path: &str) -> Result<Vec<ReadingListItem>, SafariError> {
+        let bookmarks = open_plist(path)?;
+        let archive_directory = Path::new(path)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join("ReadingListArchives");
+
+        let mut reading_list: Vec<ReadingListItem> = Vec::new();
+        let folders = get_array(&bookmarks, "Children").map_or(&[][..], Vec::as_slice);
+        for folder in folders {
+            let folder_dict = match folder.as_dictionary() {
+                Some(results) => results,
+                None => continue,
+            };
+            if get_string(folder_dict, "Title").as_deref() != Some(READING_LIST_TITLE) {
+                continue;
+            }
+
+            let items = get_array(folder_dict, "Children").map_or(&[][..], Vec::as_slice);
+            for item in items {
+                match item.as_dictionary() {
+                    Some(item_dict) => reading_list
+                        .push(SafariReadingList::get_item(item_dict, &archive_directory)),
+                    None => warn!("Safari Reading List item is not a dictionary"),
+                }
+            }
+        }
+        Ok(reading_list)
+    }
+
+    // Parse a Reading List bookmark dictionary
+    fn get_item(dict: &Dictionary, archive_directory: &Path) -> ReadingListItem {
+        let uuid = get_string(dict, "WebBookmarkUUID").unwrap_or_default();
+        let empty_dict = Dictionary::new();
+        let reading_list = get_dictionary(dict, "ReadingList").unwrap_or(&empty_dict);
+        let non_sync = get_dictionary(dict, "ReadingListNonSync").unwrap_or(&empty_dict);
+
+        let archive = archive_directory.join(&uuid);
+        let archive_path = if !uuid.is_empty() && archive.is_dir() {
+            Some(archive.display().to_string())
+        } else {
+            None
+        };
+
+        ReadingListItem {
+            title: get_dictionary(dict, "URIDictionary")
+                .and_then(|uri| get_string(uri, "title"))
+                .unwrap_or_default(),
+            url: get_string(dict, "URLString").unwrap_or_default(),
+            date_added: get_date(reading_list, "DateAdded"),
+            date_last_viewed: get_date(reading_list, "DateLastViewed"),
+            date_last_fetched: get_date(reading_list, "DateLastFetched")
+                .or_else(|| get_date(non_sync, "DateLastFetched")),
+            preview_text: get_string(reading_list, "PreviewText"),
+            source_bundle_id: get_string(reading_list, "sourceBundleID"),
+            source_app_name: get_string(reading_list, "sourceLocalizedAppName"),
+            site_name: get_string(non_sync, "siteName"),
+            image_url: get_string(dict, "imageURL"),
+            archive_path,
+            uuid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::SafariReadingList;
+
+    #[test]
+    fn test_get_reading_list() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Bookmarks.plist");
+        let results =
+            SafariReadingList::get_reading_list(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].uuid, "28CAD2E4-6F70-4192-935E-7F9A1B3C5D6E");
+        assert_eq!(results[0].title, "Example article");
+        assert_eq!(
+            results[0].url,
+            "https://www.cnn.com/2022/06/26/tech/example-article/index.html"
+        );
+        assert_eq!(results[0].date_added, Some(1656265212.0));
+        assert_eq!(results[0].date_last_viewed, Some(1656265263.0));
+        assert_eq!(results[0].date_last_fetched, Some(1656265220.0));
+        assert_eq!(
+            results[0].preview_text.as_deref(),
+            Some("An example article saved for later")
+        );
+        assert_eq!(
+            results[0].source_bundle_id.as_deref(),
+            Some("com.apple.Safari")
+        );
+        assert_eq!(results[0].site_name.as_deref(), Some("CNN"));
+        assert!(results[0]
+            .archive_path
+            .as_ref()
+            .unwrap()
+            .ends_with("ReadingListArchives/28CAD2E4-6F70-4192-935E-7F9A1B3C5D6E"));
+
+        assert_eq!(results[1].date_added, Some(1656265320.0));
+        assert_eq!(results[1].date_last_viewed, None);
+        assert_eq!(results[1].source_bundle_id, None);
+        assert_eq!(results[1].archive_path, None);
+    }
+}
@@ -0,0 +1,150 @@
+//! Parse the Safari RecentlyClosedTabs PLIST file
+//!
+//! Contains the tabs and windows the user recently closed, with the date they were closed.
+//! Tabs and windows use the same model as the LastSession PLIST file
+
+use log::warn;
+use serde::Serialize;
+
+use crate::{
+    error::SafariError,
+    plist_values::{get_array, get_dictionary, get_int, open_plist},
+    session::{get_tab, get_window, SessionTab, SessionWindow},
+    users::get_user_files,
+};
+
+#[derive(Debug, Serialize)]
+pub struct SafariRecentlyClosed {
+    pub results: Vec<ClosedState>,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub enum ClosedType {
+    Tab,
+    Window,
+    Unknown,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClosedState {
+    pub closed_type: ClosedType,
+    pub date_closed: Option<f64>,      // UNIX Epoch seconds
+    pub tab: Option<SessionTab>,       // Set when a single tab was closed
+    pub window: Option<SessionWindow>, // Set when a whole window was closed
+}
+
+impl SafariRecentlyClosed {
+    /// Get Safari RecentlyClosedTabs PLIST file for all users
+    pub fn get_users_recently_closed() -> Result<Vec<SafariRecentlyClosed>, SafariError> {
+        let user_files = get_user_files("/Library/Safari/RecentlyClosedTabs.plist")?;
+
+        let mut safari_closed: Vec<SafariRecentlyClosed> = Vec::new();
+        for user_file in user_files {
+            let results = SafariRecentlyClosed::get_recently_closed(&user_file.path)?;
+            let closed = SafariRecentlyClosed {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_closed.push(closed);
+        }
+        Ok(safari_closed)
+    }
+
+    /// Parse Safari RecentlyClosedTabs PLIST file at provided path
+    pub fn get_recently_closed(path: &str) -> Result<Vec<ClosedState>, SafariError> {
+        let closed_plist = open_plist(path)?;
+
+        let mut closed_states: Vec<ClosedState> = Vec::new();
+        let states = match get_array(&closed_plist, "ClosedTabOrWindowPersistentStates") {
+            Some(results) => results,
+            None => return Ok(closed_states),
+        };
+
+        for state in states {
+            let state_dict = match state.as_dictionary() {
+                Some(results) => results,
+                None => {
+                    warn!("Safari closed tab state is not a dictionary");
+                    continue;
+                }
+            };
+            let persistent_state = match get_dictionary(state_dict, "PersistentState") {
+                Some(results) => results,
+                None => {
+                    warn!("No persistent state for Safari closed tab");
+                    continue;
+                }
+            };
+
+            let closed_state = match get_int(state_dict, "PersistentStateType") {
+                Some(0) => {
+                    let tab = get_tab(persistent_state);
+                    ClosedState {
+                        closed_type: ClosedType::Tab,
+                        date_closed: tab.date_closed,
+                        tab: Some(tab),
+                        window: None,
+                    }
+                }
+                Some(1) => {
+                    let window = get_window(persistent_state);
+                    ClosedState {
+                        closed_type: ClosedType::Window,
+                        date_closed: window.date_closed,
+                        tab: None,
+                        window: Some(window),
+                    }
+                }
+                state_type => {
+                    warn!("Unknown Safari closed state type: {:?}", state_type);
+                    ClosedState {
+                        closed_type: ClosedType::Unknown,
+                        date_closed: None,
+                        tab: None,
+                        window: None,
+                    }
+                }
+            };
+            closed_states.push(closed_state);
+        }
+        Ok(closed_states)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{ClosedType, SafariRecentlyClosed};
+
+    #[test]
+    fn test_get_recently_closed() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/RecentlyClosedTabs.plist");
+        let results =
+            SafariRecentlyClosed::get_recently_closed(&test_location.display().to_string())
+                .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].closed_type, ClosedType::Tab);
+        assert_eq!(results[0].date_closed, Some(1656266440.0));
+        assert!(results[0].window.is_none());
+
+        let tab = results[0].tab.as_ref().unwrap();
+        assert_eq!(tab.title, "Installing PowerShell on macOS");
+        assert_eq!(tab.date_closed, Some(1656266440.0));
+        assert_eq!(tab.history.len(), 2);
+        assert_eq!(tab.history[0].url, "https://aka.ms/powershell");
+
+        assert_eq!(results[1].closed_type, ClosedType::Window);
+        assert_eq!(results[1].date_closed, Some(1656264600.0));
+        let window = results[1].window.as_ref().unwrap();
+        assert_eq!(window.selected_tab_index, Some(1));
+        assert_eq!(window.tabs.len(), 2);
+        assert_eq!(window.tabs[0].history[0].title, "CNN");
+        assert!(window.tabs[1].history.is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+//! Parse the Safari SafariTabs SQLITE file
+//!
+//! Since Safari 15 open tabs and Tab Groups are stored in a bookmarks style `bookmarks` table.
+//! Folders (type 1) are Tab Groups or Profiles (subtype 2) and leaves (type 0) are the tabs in a Tab Group.
+//! Pinned tabs are stored in their own folder
+
+use std::io::Cursor;
+
+use log::{error, warn};
+use plist::Value;
+use serde::Serialize;
+
+use crate::{
+    error::SafariError, sqlite::open_sqlite, time::cocoa_to_unixepoch, users::get_user_files,
+};
+
+const TABS_QUERY: &str = "SELECT id, parent, type, subtype, title, url, order_index, external_uuid, last_modified, local_attributes FROM bookmarks WHERE deleted = 0 ORDER BY parent, order_index";
+
+// Bookmark types and subtypes used by SafariTabs.db
+const FOLDER_TYPE: i64 = 1;
+const TAB_TYPE: i64 = 0;
+const PROFILE_SUBTYPE: i64 = 2;
+const PINNED_TABS_TITLE: &str = "com.apple.Safari.PinnedTabs";
+
+#[derive(Debug, Serialize)]
+pub struct SafariTabs {
+    pub results: Vec<TabGroup>,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TabGroup {
+    pub id: i64,
+    pub uuid: Option<String>,
+    pub title: String,
+    pub profile: Option<String>,    // None for the default profile
+    pub is_pinned: bool,            // Folder containing the pinned tabs
+    pub last_modified: Option<f64>, // UNIX Epoch seconds
+    pub tabs: Vec<Tab>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Tab {
+    pub id: i64,
+    pub uuid: Option<String>,
+    pub title: String,
+    pub url: String,
+    pub order_index: i64,
+    pub last_viewed: Option<f64>, // UNIX Epoch seconds, from the local attributes
+    pub last_modified: Option<f64>, // UNIX Epoch seconds
+    pub is_pinned: bool,
+    pub profile: Option<String>, // None for the default profile
+}
+
+// Row in the bookmarks table
+struct TabRow {
+    id: i64,
+    parent: Option<i64>,
+    row_type: i64,
+    subtype: i64,
+    title: Option<String>,
+    url: Option<String>,
+    order_index: i64,
+    uuid: Option<String>,
+    last_modified: Option<f64>,
+    local_attributes: Option<Vec<u8>>,
+}
+
+impl SafariTabs {
+    /// Get Safari SafariTabs SQLITE file for all users
+    pub fn get_users_tabs() -> Result<Vec<SafariTabs>, SafariError> {
+        let user_files = get_user_files(
+            "/Library/Containers/com.apple.Safari/Data/Library/Safari/SafariTabs.db",
+        )?;
+
+        let mut safari_tabs: Vec<SafariTabs> = Vec::new();
+        for user_file in user_files {
+            let results = SafariTabs::get_tabs(&user_file.path)?;
+            let tabs = SafariTabs {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_tabs.push(tabs);
+        }
+        Ok(safari_tabs)
+    }
+
+    /// Query the Tab Groups and their tabs based on provided path
+    pub fn get_tabs(path: &str) -> Result<Vec<TabGroup>, SafariError> {
+        let conn = open_sqlite(path)?;
+        let statement = conn.prepare(TABS_QUERY);
+        let mut stmt = match statement {
+            Ok(query) => query,
+            Err(err) => {
+                error!("Failed to compose Safari tabs SQL query {:?}", err);
+                return Err(SafariError::BadSQL);
+            }
+        };
+
+        let tabs_data = stmt.query_map([], |row| {
+            Ok(TabRow {
+                id: row.get("id")?,
+                parent: row.get("parent")?,
+                row_type: row.get("type")?,
+                subtype: row.get("subtype")?,
+                title: row.get("title")?,
+                url: row.get("url")?,
+                order_index: row.get("order_index")?,
+                uuid: row.get("external_uuid")?,
+                last_modified: row.get("last_modified")?,
+                local_attributes: row.get("local_attributes")?,
+            })
+        });
+
+        let mut rows: Vec<TabRow> = Vec::new();
+        match tabs_data {
+            Ok(tabs_iter) => {
+                for tab in tabs_iter {
+                    match tab {
+                        Ok(tab_row) => rows.push(tab_row),
+                        Err(err) => {
+                            warn!("Failed to iterate through Safari tabs data: {:?}", err);
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Failed to get Safari tabs data from SQLITE file: {:?}", err);
+                return Err(SafariError::SqliteParse);
+            }
+        }
+        Ok(SafariTabs::get_tab_groups(&rows))
+    }
+
+    // Group the tabs under their Tab Group folder
+    fn get_tab_groups(rows: &[TabRow]) -> Vec<TabGroup> {
+        let mut tab_groups: Vec<TabGroup> = Vec::new();
+        for row in rows {
+            // The root folder has no parent
+            if row.row_type != FOLDER_TYPE || row.subtype == PROFILE_SUBTYPE || row.parent.is_none()
+            {
+                continue;
+            }
+
+            let profile = rows
+                .iter()
+                .find(|profile| {
+                    Some(profile.id) == row.parent
+                        && profile.row_type == FOLDER_TYPE
+                        && profile.subtype == PROFILE_SUBTYPE
+                })
+                .and_then(|profile| profile.title.clone());
+            let title = row.title.clone().unwrap_or_default();
+            let is_pinned = title == PINNED_TABS_TITLE;
+
+            let tabs = rows
+                .iter()
+                .filter(|tab| tab.row_type == TAB_TYPE && tab.parent == Some(row.id))
+                .map(|tab| Tab {
+                    id: tab.id,
+                    uuid: tab.uuid.clone(),
+                    title: tab.title.clone().unwrap_or_default(),
+                    url: tab.url.clone().unwrap_or_default(),
+                    order_index: tab.order_index,
+                    last_viewed: tab
+                        .local_attributes
+                        .as_deref()
+                        .and_then(get_last_visit_time),
+                    last_modified: tab.last_modified.map(cocoa_to_unixepoch),
+                    is_pinned,
+                    profile: profile.clone(),
+                })
+                .collect();
+
+            tab_groups.push(TabGroup {
+                id: row.id,
+                uuid: row.uuid.clone(),
+                title,
+                profile,
+                is_pinned,
+                last_modified: row.last_modified.map(cocoa_to_unixepoch),
+                tabs,
+            });
+        }
+        tab_groups
+    }
+}
+
+// Get the last time the tab was viewed from the local attributes PLIST
+fn get_last_visit_time(local_attributes: &[u8]) -> Option<f64> {
+    match Value::from_reader(Cursor::new(local_attributes)) {
+        Ok(Value::Dictionary(attributes)) => attributes
+            .get("LastVisitTime")
+            .and_then(Value::as_real)
+            .map(cocoa_to_unixepoch),
+        Ok(_) => None,
+        Err(err) => {
+            warn!("Failed to parse Safari tab local attributes: {:?}", err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{get_last_visit_time, SafariTabs};
+
+    #[test]
+    fn test_get_tabs() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/SafariTabs.db");
+        let results = SafariTabs::get_tabs(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].title, "Research");
+        assert_eq!(results[0].profile, None);
+        assert!(!results[0].is_pinned);
+        assert_eq!(results[0].last_modified, Some(1656266000.0));
+
+        // Deleted tabs are skipped
+        assert_eq!(results[0].tabs.len(), 2);
+        let tab = &results[0].tabs[0];
+        assert_eq!(
+            tab.url,
+            "https://github.com/PowerShell/PowerShell/releases/tag/v7.2.5"
+        );
+        assert_eq!(tab.title, "Release v7.2.5 · PowerShell/PowerShell");
+        assert_eq!(
+            tab.uuid.as_deref(),
+            Some("5E555555-5555-4555-8555-555555555555")
+        );
+        assert_eq!(tab.last_viewed, Some(1656266410.5));
+        assert_eq!(tab.last_modified, Some(1656266400.0));
+        // No local attributes, the last modified time is not a view time
+        assert_eq!(results[0].tabs[1].last_viewed, None);
+        assert_eq!(results[0].tabs[1].last_modified, Some(1656265000.0));
+
+        assert_eq!(results[1].title, "com.apple.Safari.PinnedTabs");
+        assert!(results[1].is_pinned);
+        assert!(results[1].tabs[0].is_pinned);
+        assert_eq!(results[1].tabs[0].url, "https://www.youtube.com/");
+
+        assert_eq!(results[2].title, "Incident");
+        assert_eq!(results[2].profile.as_deref(), Some("Work"));
+        assert_eq!(results[2].tabs[0].profile.as_deref(), Some("Work"));
+        assert_eq!(results[2].tabs[0].last_viewed, Some(1656264100.0));
+    }
+
+    #[test]
+    fn test_get_last_visit_time() {
+        assert_eq!(get_last_visit_time(b"bad"), None);
+    }
+}
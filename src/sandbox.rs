@@ -0,0 +1,215 @@
+//! Resolve Safari download sandbox identifiers to app containers
+//!
+//! Each app and group container has a metadata PLIST with the container UUID and the app (or group) identifier.
+//! Containers can be read from the live system or from the root of a mounted image
+
+use std::{fs::read_dir, path::Path};
+
+use log::{error, warn};
+use plist::{Dictionary, Value};
+use serde::Serialize;
+
+use crate::{downloads::Downloads, error::SafariError};
+
+const CONTAINER_METADATA: &str = ".com.apple.containermanagerd.metadata.plist";
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub enum ContainerType {
+    App,   // Library/Containers
+    Group, // Library/Group Containers
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SandboxContainer {
+    pub uuid: String,
+    pub identifier: String, // App bundle or group identifier
+    pub container_type: ContainerType,
+    pub path: String, // Container directory
+    pub user: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SandboxDownload {
+    pub download_id: String,
+    pub sandbox_id: String,
+    pub container: Option<SandboxContainer>, // None if no container has the sandbox identifier
+}
+
+impl SandboxContainer {
+    /// Get the app and group containers for all users under `image_root`. Use "/" for the live system
+    pub fn get_users_containers(image_root: &str) -> Result<Vec<SandboxContainer>, SafariError> {
+        let base_directory = Path::new(image_root).join("Users");
+        let users = match read_dir(&base_directory) {
+            Ok(dir) => dir,
+            Err(err) => {
+                error!(
+                    "Failed to read base directory {}: {:?}",
+                    base_directory.display(),
+                    err
+                );
+                return Err(SafariError::Path);
+            }
+        };
+
+        let mut containers: Vec<SandboxContainer> = Vec::new();
+        for entry in users {
+            match entry {
+                Ok(entry_result) => {
+                    let user = entry_result.file_name().to_string_lossy().to_string();
+                    containers.append(&mut SandboxContainer::get_containers(
+                        &entry_result.path().display().to_string(),
+                        &user,
+                    ));
+                }
+                Err(err) => warn!("Failed to get user directory: {:?}", err),
+            }
+        }
+        Ok(containers)
+    }
+
+    /// Get the app and group containers in a user home directory
+    pub fn get_containers(home_directory: &str, user: &str) -> Vec<SandboxContainer> {
+        let container_directories = [
+            ("Library/Containers", ContainerType::App),
+            ("Library/Group Containers", ContainerType::Group),
+        ];
+
+        let mut containers: Vec<SandboxContainer> = Vec::new();
+        for (directory, container_type) in container_directories {
+            let container_directory = Path::new(home_directory).join(directory);
+            let entries = match read_dir(&container_directory) {
+                Ok(dir) => dir,
+                // Not every user has group containers
+                Err(_) => continue,
+            };
+
+            for entry in entries.flatten() {
+                let metadata_path = entry.path().join(CONTAINER_METADATA);
+                if !metadata_path.is_file() {
+                    continue;
+                }
+
+                let metadata_results: Result<Dictionary, plist::Error> =
+                    plist::from_file(&metadata_path);
+                let metadata = match metadata_results {
+                    Ok(results) => results,
+                    Err(err) => {
+                        warn!(
+                            "Failed to parse container metadata {}: {:?}",
+                            metadata_path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+
+                let (uuid, identifier) = match (
+                    metadata.get("MCMMetadataUUID").and_then(Value::as_string),
+                    metadata
+                        .get("MCMMetadataIdentifier")
+                        .and_then(Value::as_string),
+                ) {
+                    (Some(uuid), Some(identifier)) => (uuid, identifier),
+                    _ => {
+                        warn!(
+                            "Missing container UUID or identifier in {}",
+                            metadata_path.display()
+                        );
+                        continue;
+                    }
+                };
+
+                let container = SandboxContainer {
+                    uuid: uuid.to_string(),
+                    identifier: identifier.to_string(),
+                    container_type: container_type.clone(),
+                    path: entry.path().display().to_string(),
+                    user: user.to_string(),
+                };
+                containers.push(container);
+            }
+        }
+        containers
+    }
+}
+
+impl SandboxDownload {
+    /// Resolve the sandbox identifier of each download. Containers should come from the same user as the downloads
+    pub fn resolve_downloads(
+        downloads: &[Downloads],
+        containers: &[SandboxContainer],
+    ) -> Vec<SandboxDownload> {
+        downloads
+            .iter()
+            .map(|download| SandboxDownload {
+                download_id: download.download_id.clone(),
+                sandbox_id: download.sandbox_id.clone(),
+                container: containers
+                    .iter()
+                    .find(|container| container.uuid.eq_ignore_ascii_case(&download.sandbox_id))
+                    .cloned(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{ContainerType, SandboxContainer, SandboxDownload};
+    use crate::downloads::SafariDownloads;
+
+    #[test]
+    fn test_get_users_containers() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/SandboxImage");
+        let mut results =
+            SandboxContainer::get_users_containers(&test_location.display().to_string()).unwrap();
+        results.sort_by(|first, second| first.identifier.cmp(&second.identifier));
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].identifier, "com.apple.Safari");
+        assert_eq!(results[0].uuid, "DBA9EBA4-D23B-43C5-9DEB-131566E7BD8B");
+        assert_eq!(results[0].container_type, ContainerType::App);
+        assert_eq!(results[0].user, "puffycid");
+        assert!(results[0]
+            .path
+            .ends_with("Users/puffycid/Library/Containers/com.apple.Safari"));
+        assert_eq!(results[2].container_type, ContainerType::Group);
+    }
+
+    #[test]
+    fn test_resolve_downloads() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/SandboxImage/Users/puffycid");
+        let containers =
+            SandboxContainer::get_containers(&test_location.display().to_string(), "puffycid");
+
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let downloads =
+            SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
+
+        let results = SandboxDownload::resolve_downloads(&downloads, &containers);
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].container.as_ref().unwrap().identifier,
+            "com.apple.Safari"
+        );
+        assert_eq!(
+            results[1].container.as_ref().unwrap().identifier,
+            "group.com.apple.Safari.SandboxBroker"
+        );
+        assert!(results[2].container.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Path")]
+    fn test_get_users_containers_bad_root() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/missing");
+        let _ =
+            SandboxContainer::get_users_containers(&test_location.display().to_string()).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+//! Parse the Safari LastSession PLIST file
+//!
+//! Contains the windows and tabs that were open when Safari last saved its session. Each tab has a session state blob
+//! with the back/forward list of the tab, which can contain pages that are no longer in History.db.
+//! Compressed session state blobs are flagged and returned raw instead of being decoded
+
+use std::io::Cursor;
+
+use log::warn;
+use plist::{Dictionary, Value};
+use serde::Serialize;
+
+use crate::{
+    error::SafariError,
+    plist_values::{
+        get_array, get_bool, get_data, get_date, get_dictionary, get_int, get_string, open_plist,
+    },
+    time::cocoa_to_unixepoch,
+    users::get_user_files,
+};
+
+#[derive(Debug, Serialize)]
+pub struct SafariSession {
+    pub results: Vec<SessionWindow>,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionWindow {
+    pub uuid: Option<String>,
+    pub is_private: bool,
+    pub selected_tab_index: Option<i64>,
+    pub date_closed: Option<f64>, // UNIX Epoch seconds, only set for closed windows
+    pub tabs: Vec<SessionTab>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionTab {
+    pub uuid: Option<String>,
+    pub title: String,
+    pub url: String,
+    pub is_pinned: bool,
+    pub last_visit_time: Option<f64>,      // UNIX Epoch seconds
+    pub date_closed: Option<f64>,          // UNIX Epoch seconds, only set for closed tabs
+    pub history_index: Option<i64>,        // Index of the current page in the back/forward list
+    pub history: Vec<SessionHistoryEntry>, // Back/forward list of the tab, empty if the session state is compressed
+    pub session_state_compressed: bool,    // Compressed session state is not decoded
+    pub compressed_session_state: Option<Vec<u8>>, // Raw session state blob, only set when compressed
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionHistoryEntry {
+    pub url: String,
+    pub original_url: Option<String>, // URL before any redirects
+    pub title: String,
+}
+
+impl SafariSession {
+    /// Get Safari LastSession PLIST file for all users
+    pub fn get_users_last_session() -> Result<Vec<SafariSession>, SafariError> {
+        let user_files = get_user_files("/Library/Safari/LastSession.plist")?;
+
+        let mut safari_sessions: Vec<SafariSession> = Vec::new();
+        for user_file in user_files {
+            let results = SafariSession::get_last_session(&user_file.path)?;
+            let session = SafariSession {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_sessions.push(session);
+        }
+        Ok(safari_sessions)
+    }
+
+    /// Parse Safari LastSession PLIST file at provided path
+    pub fn get_last_session(path: &str) -> Result<Vec<SessionWindow>, SafariError> {
+        let session = open_plist(path)?;
+
+        let mut windows: Vec<SessionWindow> = Vec::new();
+        if let Some(window_values) = get_array(&session, "SessionWindows") {
+            for window in window_values {
+                match window.as_dictionary() {
+                    Some(window_dict) => windows.push(get_window(window_dict)),
+                    None => warn!("Safari session window is not a dictionary"),
+                }
+            }
+        }
+        Ok(windows)
+    }
+}
+
+// Parse a session window dictionary and its tabs
+pub(crate) fn get_window(dict: &Dictionary) -> SessionWindow {
+    let mut tabs: Vec<SessionTab> = Vec::new();
+    if let Some(tab_values) = get_array(dict, "TabStates") {
+        for tab in tab_values {
+            match tab.as_dictionary() {
+                Some(tab_dict) => tabs.push(get_tab(tab_dict)),
+                None => warn!("Safari session tab is not a dictionary"),
+            }
+        }
+    }
+
+    SessionWindow {
+        uuid: get_string(dict, "WindowUUID"),
+        is_private: get_bool(dict, "IsPrivateWindow").unwrap_or(false),
+        selected_tab_index: get_int(dict, "SelectedTabIndex"),
+        date_closed: get_date(dict, "DateClosed"),
+        tabs,
+    }
+}
+
+// Parse a session tab dictionary
+pub(crate) fn get_tab(dict: &Dictionary) -> SessionTab {
+    let mut tab = SessionTab {
+        uuid: get_string(dict, "TabUUID"),
+        title: get_string(dict, "TabTitle").unwrap_or_default(),
+        url: get_string(dict, "TabURL").unwrap_or_default(),
+        is_pinned: get_bool(dict, "IsAppTab").unwrap_or(false),
+        // Usually stored as a Cocoa timestamp number instead of a PLIST date
+        last_visit_time: dict
+            .get("LastVisitTime")
+            .and_then(Value::as_real)
+            .map(cocoa_to_unixepoch)
+            .or_else(|| get_date(dict, "LastVisitTime")),
+        date_closed: get_date(dict, "DateClosed"),
+        history_index: None,
+        history: Vec::new(),
+        session_state_compressed: get_bool(dict, "SessionStateIsCompressed").unwrap_or(false),
+        compressed_session_state: None,
+    };
+
+    if let Some(session_state) = get_data(dict, "SessionState") {
+        // Keep the raw blob so callers can tell a compressed back/forward list apart from an empty one
+        if tab.session_state_compressed {
+            warn!("Compressed Safari session state is not decoded, keeping the raw blob");
+            tab.compressed_session_state = Some(session_state);
+            return tab;
+        }
+        if let Some(state) = get_session_state(&session_state) {
+            if let Some(history) = get_dictionary(&state, "SessionHistory") {
+                tab.history_index = get_int(history, "SessionHistoryCurrentIndex");
+                tab.history = get_history_entries(history);
+            }
+        }
+    }
+    tab
+}
+
+// The session state blob is a small header followed by a binary PLIST
+fn get_session_state(session_state: &[u8]) -> Option<Dictionary> {
+    let bplist_signature = b"bplist00";
+    let offset = match session_state
+        .windows(bplist_signature.len())
+        .position(|window| window == bplist_signature)
+    {
+        Some(results) => results,
+        None => {
+            warn!("No PLIST data in Safari session state");
+            return None;
+        }
+    };
+
+    match Value::from_reader(Cursor::new(&session_state[offset..])) {
+        Ok(Value::Dictionary(results)) => Some(results),
+        Ok(_) => {
+            warn!("Safari session state is not a dictionary");
+            None
+        }
+        Err(err) => {
+            warn!("Failed to parse Safari session state: {:?}", err);
+            None
+        }
+    }
+}
+
+// Get the back/forward list entries from the session history
+fn get_history_entries(history: &Dictionary) -> Vec<SessionHistoryEntry> {
+    let mut entries: Vec<SessionHistoryEntry> = Vec::new();
+    if let Some(entry_values) = get_array(history, "SessionHistoryEntries") {
+        for entry in entry_values {
+            let entry_dict = match entry.as_dictionary() {
+                Some(results) => results,
+                None => continue,
+            };
+            entries.push(SessionHistoryEntry {
+                url: get_string(entry_dict, "SessionHistoryEntryURL").unwrap_or_default(),
+                original_url: get_string(entry_dict, "SessionHistoryEntryOriginalURL"),
+                title: get_string(entry_dict, "SessionHistoryEntryTitle").unwrap_or_default(),
+            });
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{get_session_state, SafariSession};
+
+    #[test]
+    fn test_get_last_session() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/LastSession.plist");
+        let results =
+            SafariSession::get_last_session(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0].uuid.as_deref(),
+            Some("A0B1C2D3-0000-4E5F-8A9B-0C1D2E3F4A5B")
+        );
+        assert!(!results[0].is_private);
+        assert_eq!(results[0].selected_tab_index, Some(0));
+        assert_eq!(results[0].date_closed, None);
+        assert_eq!(results[0].tabs.len(), 2);
+        assert!(results[1].is_private);
+
+        let tab = &results[0].tabs[0];
+        assert_eq!(
+            tab.url,
+            "https://github.com/PowerShell/PowerShell/releases/tag/v7.2.5"
+        );
+        assert_eq!(tab.last_visit_time, Some(1656266410.5));
+        assert!(!tab.is_pinned);
+        assert_eq!(tab.history_index, Some(1));
+        assert_eq!(tab.history.len(), 3);
+        assert_eq!(tab.history[0].title, "powershell mac - Google Search");
+        assert_eq!(
+            tab.history[1].original_url.as_deref(),
+            Some("https://github.com/PowerShell/PowerShell/releases")
+        );
+        assert_eq!(
+            tab.history[2].url,
+            "https://pastebin.example.com/deleted-page"
+        );
+
+        // Tab with a session state that does not contain a PLIST
+        let pinned_tab = &results[0].tabs[1];
+        assert!(pinned_tab.is_pinned);
+        assert_eq!(pinned_tab.title, "YouTube");
+        assert!(pinned_tab.history.is_empty());
+        assert!(!pinned_tab.session_state_compressed);
+        assert_eq!(pinned_tab.compressed_session_state, None);
+
+        let compressed_tab = &results[1].tabs[1];
+        assert_eq!(compressed_tab.url, "https://example.com/");
+        assert!(compressed_tab.session_state_compressed);
+        assert!(compressed_tab.history.is_empty());
+        assert!(compressed_tab
+            .compressed_session_state
+            .as_ref()
+            .unwrap()
+            .ends_with(b"compressed"));
+    }
+
+    #[test]
+    fn test_get_session_state() {
+        assert!(get_session_state(&[2, 0, 0, 0]).is_none());
+        assert!(get_session_state(b"\x02\x00\x00\x00bplist00bad").is_none());
+    }
+}
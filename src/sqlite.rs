@@ -0,0 +1,21 @@
+use log::error;
+use rusqlite::{Connection, OpenFlags};
+
+use crate::error::SafariError;
+
+// Open a SQLITE file read-only
+pub(crate) fn open_sqlite(path: &str) -> Result<Connection, SafariError> {
+    // Bypass SQLITE file lock
+    let sqlite_file = format!("file:{}?immutable=1", path);
+    let connection = Connection::open_with_flags(
+        sqlite_file,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    );
+    match connection {
+        Ok(connect) => Ok(connect),
+        Err(err) => {
+            error!("Failed to read SQLITE file {}: {:?}", path, err);
+            Err(SafariError::SqliteParse)
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// Seconds between UNIX Epoch (1970-01-01) and Apple Cocoa Core Data epoch (2001-01-01)
+const COCOA_EPOCH_OFFSET: f64 = 978307200.0;
+
+// Convert an Apple Cocoa Core Data timestamp to UNIX Epoch seconds
+pub(crate) fn cocoa_to_unixepoch(timestamp: f64) -> f64 {
+    timestamp + COCOA_EPOCH_OFFSET
+}
+
+// Convert a compact ISO8601 UTC timestamp (ex: 20220626T180026Z) to UNIX Epoch seconds
+pub(crate) fn compact_iso8601_to_unixepoch(timestamp: &str) -> Option<f64> {
+    let bytes = timestamp.as_bytes();
+    if bytes.len() != 16 || bytes[8] != b'T' || bytes[15] != b'Z' {
+        return None;
+    }
+    // Parsing numbers would also accept a leading sign
+    if !bytes[0..8]
+        .iter()
+        .chain(&bytes[9..15])
+        .all(u8::is_ascii_digit)
+    {
+        return None;
+    }
+    let year: i64 = timestamp[0..4].parse().ok()?;
+    let month: i64 = timestamp[4..6].parse().ok()?;
+    let day: i64 = timestamp[6..8].parse().ok()?;
+    let hour: i64 = timestamp[9..11].parse().ok()?;
+    let minute: i64 = timestamp[11..13].parse().ok()?;
+    let second: i64 = timestamp[13..15].parse().ok()?;
+
+    let is_leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year => 29,
+        2 => 28,
+        _ => return None,
+    };
+    if !(1..=days_in_month).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    // Days since 1970-01-01 using the civil calendar algorithm from Howard Hinnant
+    let adjusted_year = if month <= 2 { year - 1 } else { year };
+    let era = adjusted_year.div_euclid(400);
+    let year_of_era = adjusted_year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    Some((days * 86400 + hour * 3600 + minute * 60 + second) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cocoa_to_unixepoch, compact_iso8601_to_unixepoch};
+
+    #[test]
+    fn test_cocoa_to_unixepoch() {
+        let result = cocoa_to_unixepoch(677386043.546784);
+        assert_eq!(result, 1655693243.546784);
+    }
+
+    #[test]
+    fn test_compact_iso8601_to_unixepoch() {
+        assert_eq!(
+            compact_iso8601_to_unixepoch("20220626T180026Z"),
+            Some(1656266426.0)
+        );
+        assert_eq!(compact_iso8601_to_unixepoch("19700101T000000Z"), Some(0.0));
+        assert_eq!(
+            compact_iso8601_to_unixepoch("20000229T120000Z"),
+            Some(951825600.0)
+        );
+        assert_eq!(compact_iso8601_to_unixepoch("2022-06-26"), None);
+        // Missing Z suffix
+        assert_eq!(compact_iso8601_to_unixepoch("20220626T1800260"), None);
+        assert_eq!(compact_iso8601_to_unixepoch("20220626T180026"), None);
+        // Out of range fields
+        assert_eq!(compact_iso8601_to_unixepoch("20221326T180026Z"), None);
+        assert_eq!(compact_iso8601_to_unixepoch("20220230T180026Z"), None);
+        assert_eq!(compact_iso8601_to_unixepoch("20220626T240026Z"), None);
+        assert_eq!(compact_iso8601_to_unixepoch("20220626T186026Z"), None);
+        assert_eq!(compact_iso8601_to_unixepoch("20220626T180060Z"), None);
+        assert_eq!(compact_iso8601_to_unixepoch("20220626T+80026Z"), None);
+    }
+}
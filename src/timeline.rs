@@ -0,0 +1,233 @@
+//! Combine parsed Safari artifacts into a single timeline
+//!
+//! All timestamps are converted to UNIX Epoch seconds and events are sorted oldest first
+
+use serde::Serialize;
+
+use crate::{
+    downloads::SafariDownloads, history::SafariHistory, reading_list::SafariReadingList,
+    time::cocoa_to_unixepoch,
+};
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub enum EventType {
+    HistoryVisit,
+    DownloadStart,
+    DownloadFinish,
+    ReadingListAdded,
+    ReadingListViewed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TimelineEvent {
+    pub timestamp: f64, // UNIX Epoch seconds
+    pub event_type: EventType,
+    pub description: String,
+    pub url: String,
+    pub user: String,
+    pub source: String, // File the event was parsed from
+}
+
+/// Safari artifacts that can be converted to timeline events
+pub trait TimelineSource {
+    fn timeline_events(&self) -> Vec<TimelineEvent>;
+}
+
+impl TimelineSource for SafariHistory {
+    fn timeline_events(&self) -> Vec<TimelineEvent> {
+        let mut events: Vec<TimelineEvent> = Vec::new();
+        for history in &self.results {
+            let title = history.title.as_deref().unwrap_or_default();
+            let description = if title.is_empty() {
+                format!("Visited {}", history.url)
+            } else {
+                format!("Visited {}", title)
+            };
+
+            let event = TimelineEvent {
+                timestamp: cocoa_to_unixepoch(history.visit_time),
+                event_type: EventType::HistoryVisit,
+                description,
+                url: history.url.clone(),
+                user: self.user.clone(),
+                source: self.path.clone(),
+            };
+            events.push(event);
+        }
+        events
+    }
+}
+
+impl TimelineSource for SafariDownloads {
+    fn timeline_events(&self) -> Vec<TimelineEvent> {
+        let mut events: Vec<TimelineEvent> = Vec::new();
+        for download in &self.results {
+            if let Some(timestamp) = download.download_entry_date {
+                let event = TimelineEvent {
+                    timestamp,
+                    event_type: EventType::DownloadStart,
+                    description: format!("Download started {}", download.download_path),
+                    url: download.source_url.clone(),
+                    user: self.user.clone(),
+                    source: self.path.clone(),
+                };
+                events.push(event);
+            }
+
+            if let Some(timestamp) = download.download_entry_finish {
+                let event = TimelineEvent {
+                    timestamp,
+                    event_type: EventType::DownloadFinish,
+                    description: format!("Download finished {}", download.download_path),
+                    url: download.source_url.clone(),
+                    user: self.user.clone(),
+                    source: self.path.clone(),
+                };
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+impl TimelineSource for SafariReadingList {
+    fn timeline_events(&self) -> Vec<TimelineEvent> {
+        let mut events: Vec<TimelineEvent> = Vec::new();
+        for item in &self.results {
+            let title = if item.title.is_empty() {
+                &item.url
+            } else {
+                &item.title
+            };
+
+            if let Some(timestamp) = item.date_added {
+                let event = TimelineEvent {
+                    timestamp,
+                    event_type: EventType::ReadingListAdded,
+                    description: format!("Added to Reading List {}", title),
+                    url: item.url.clone(),
+                    user: self.user.clone(),
+                    source: self.path.clone(),
+                };
+                events.push(event);
+            }
+
+            if let Some(timestamp) = item.date_last_viewed {
+                let event = TimelineEvent {
+                    timestamp,
+                    event_type: EventType::ReadingListViewed,
+                    description: format!("Viewed Reading List {}", title),
+                    url: item.url.clone(),
+                    user: self.user.clone(),
+                    source: self.path.clone(),
+                };
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+/// Get a sorted timeline of events from all provided Safari artifacts
+pub fn get_timeline(sources: &[&dyn TimelineSource]) -> Vec<TimelineEvent> {
+    let mut timeline: Vec<TimelineEvent> = Vec::new();
+    for source in sources {
+        timeline.append(&mut source.timeline_events());
+    }
+    timeline.sort_by(|first, second| first.timestamp.total_cmp(&second.timestamp));
+    timeline
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{get_timeline, EventType, TimelineSource};
+    use crate::{
+        downloads::SafariDownloads, history::SafariHistory, reading_list::SafariReadingList,
+    };
+
+    fn get_test_history() -> SafariHistory {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/History.db");
+        let path = test_location.display().to_string();
+        SafariHistory {
+            results: SafariHistory::get_history(&path).unwrap(),
+            path,
+            user: String::from("puffycid"),
+        }
+    }
+
+    fn get_test_downloads() -> SafariDownloads {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Downloads.plist");
+        let path = test_location.display().to_string();
+        SafariDownloads {
+            results: SafariDownloads::get_downloads(&path).unwrap(),
+            path,
+            user: String::from("puffycid"),
+        }
+    }
+
+    #[test]
+    fn test_history_timeline_events() {
+        let history = get_test_history();
+        let events = history.timeline_events();
+
+        assert_eq!(events.len(), 42);
+        assert_eq!(events[0].timestamp, 1655693243.546784);
+        assert_eq!(events[0].event_type, EventType::HistoryVisit);
+        assert_eq!(events[0].description, "Visited duckduckgo - Google Search");
+        assert_eq!(events[0].user, "puffycid");
+    }
+
+    #[test]
+    fn test_downloads_timeline_events() {
+        let downloads = get_test_downloads();
+        let events = downloads.timeline_events();
+
+        assert_eq!(events.len(), 6);
+        assert_eq!(events[0].timestamp, 1656266417.8241808);
+        assert_eq!(events[0].event_type, EventType::DownloadStart);
+        assert_eq!(
+            events[1].description,
+            "Download finished /Users/puffycid/Downloads/powershell-7.2.5-osx-arm64.pkg"
+        );
+    }
+
+    #[test]
+    fn test_reading_list_timeline_events() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/Bookmarks.plist");
+        let path = test_location.display().to_string();
+        let reading_list = SafariReadingList {
+            results: SafariReadingList::get_reading_list(&path).unwrap(),
+            path,
+            user: String::from("puffycid"),
+        };
+        let events = reading_list.timeline_events();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].event_type, EventType::ReadingListAdded);
+        assert_eq!(
+            events[0].description,
+            "Added to Reading List Example article"
+        );
+        assert_eq!(events[1].event_type, EventType::ReadingListViewed);
+        assert_eq!(events[1].timestamp, 1656265263.0);
+    }
+
+    #[test]
+    fn test_get_timeline() {
+        let history = get_test_history();
+        let downloads = get_test_downloads();
+        let timeline = get_timeline(&[&history, &downloads]);
+
+        assert_eq!(timeline.len(), 48);
+        for events in timeline.windows(2) {
+            assert!(events[0].timestamp <= events[1].timestamp);
+        }
+        assert_eq!(timeline[47].event_type, EventType::HistoryVisit);
+        assert!(timeline[47].timestamp > 1656266422.0);
+    }
+}
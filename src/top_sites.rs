@@ -0,0 +1,121 @@
+//! Parse the Safari TopSites PLIST file
+//!
+//! Top sites are the sites shown on the Safari start page, including sites the user pinned or removed (banned)
+
+use log::warn;
+use serde::Serialize;
+
+use crate::{
+    error::SafariError,
+    plist_values::{get_array, get_bool, get_date, get_string, open_plist},
+    users::get_user_files,
+};
+
+#[derive(Debug, Serialize)]
+pub struct SafariTopSites {
+    pub results: TopSites,
+    pub path: String,
+    pub user: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopSites {
+    pub sites: Vec<TopSite>,
+    pub banned_urls: Vec<String>, // Sites removed from top sites by the user
+    pub last_modified: Option<f64>, // UNIX Epoch seconds
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopSite {
+    pub url: String,
+    pub title: String,
+    pub is_built_in: bool, // Default site added by Safari
+    pub is_pinned: bool,   // Pinned by the user
+}
+
+impl SafariTopSites {
+    /// Get Safari TopSites PLIST file for all users
+    pub fn get_users_top_sites() -> Result<Vec<SafariTopSites>, SafariError> {
+        let user_files = get_user_files("/Library/Safari/TopSites.plist")?;
+
+        let mut safari_top_sites: Vec<SafariTopSites> = Vec::new();
+        for user_file in user_files {
+            let results = SafariTopSites::get_top_sites(&user_file.path)?;
+            let top_sites = SafariTopSites {
+                results,
+                path: user_file.path,
+                user: user_file.user,
+            };
+            safari_top_sites.push(top_sites);
+        }
+        Ok(safari_top_sites)
+    }
+
+    /// Parse Safari TopSites PLIST file at provided path
+    pub fn get_top_sites(path: &str) -> Result<TopSites, SafariError> {
+        let top_sites_plist = open_plist(path)?;
+
+        let mut top_sites = TopSites {
+            sites: Vec::new(),
+            banned_urls: Vec::new(),
+            last_modified: get_date(&top_sites_plist, "DisplayedSitesLastModified"),
+        };
+
+        if let Some(sites) = get_array(&top_sites_plist, "TopSites") {
+            for site in sites {
+                let site_dict = match site.as_dictionary() {
+                    Some(results) => results,
+                    None => {
+                        warn!("Safari top site is not a dictionary");
+                        continue;
+                    }
+                };
+
+                let top_site = TopSite {
+                    url: get_string(site_dict, "TopSiteURLString").unwrap_or_default(),
+                    title: get_string(site_dict, "TopSiteTitle").unwrap_or_default(),
+                    is_built_in: get_bool(site_dict, "TopSiteIsBuiltIn").unwrap_or(false),
+                    is_pinned: get_bool(site_dict, "TopSiteIsPinned").unwrap_or(false),
+                };
+                top_sites.sites.push(top_site);
+            }
+        }
+
+        if let Some(banned_urls) = get_array(&top_sites_plist, "BannedURLStrings") {
+            top_sites.banned_urls = banned_urls
+                .iter()
+                .filter_map(|url| url.as_string())
+                .map(str::to_string)
+                .collect();
+        }
+        Ok(top_sites)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::SafariTopSites;
+
+    #[test]
+    fn test_get_top_sites() {
+        let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        test_location.push("tests/test_data/TopSites.plist");
+        let results = SafariTopSites::get_top_sites(&test_location.display().to_string()).unwrap();
+
+        assert_eq!(results.sites.len(), 4);
+        assert_eq!(results.sites[0].url, "https://www.apple.com/");
+        assert_eq!(results.sites[0].title, "Apple");
+        assert!(results.sites[0].is_built_in);
+        assert!(!results.sites[0].is_pinned);
+        assert!(results.sites[1].is_pinned);
+        assert!(!results.sites[2].is_built_in);
+
+        assert_eq!(
+            results.banned_urls,
+            ["https://www.youtube.com/", "https://us02web.zoom.us/"]
+        );
+        assert_eq!(results.last_modified, Some(1656266465.0));
+    }
+}
@@ -0,0 +1,61 @@
+use std::{fs::read_dir, path::Path};
+
+use log::{error, info, warn};
+
+use crate::{error::SafariError, size::get_file_size};
+
+pub(crate) struct UserFile {
+    pub(crate) user: String,
+    pub(crate) path: String,
+}
+
+// Get the provided Safari file for all users that have it
+pub(crate) fn get_user_files(file_path: &str) -> Result<Vec<UserFile>, SafariError> {
+    let base_directory = "/Users/";
+    let users = match read_dir(base_directory) {
+        Ok(dir) => dir,
+        Err(err) => {
+            error!(
+                "Failed to read base directory {}: {:?}",
+                base_directory, err
+            );
+            return Err(SafariError::Path);
+        }
+    };
+
+    let mut user_files: Vec<UserFile> = Vec::new();
+    for entry in users {
+        match entry {
+            Ok(entry_result) => {
+                let path = format!("{}{}", entry_result.path().display(), file_path);
+                // Make sure the file exists
+                if !Path::new(&path).is_file() || !get_file_size(&path) {
+                    continue;
+                }
+                info!("Parsing file path: {}", path);
+
+                let user = entry_result
+                    .path()
+                    .display()
+                    .to_string()
+                    .replace(base_directory, "");
+
+                user_files.push(UserFile { user, path });
+            }
+            Err(err) => warn!("Failed to get user directory: {:?}", err),
+        }
+    }
+    Ok(user_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_user_files;
+
+    #[test]
+    #[ignore = "Get live users Safari files"]
+    fn test_get_user_files() {
+        let result = get_user_files("/Library/Safari/History.db").unwrap();
+        assert!(result.len() > 0);
+    }
+}
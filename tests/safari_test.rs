@@ -1,6 +1,12 @@
 use std::path::PathBuf;
 
-use browser_safari::{downloads::SafariDownloads, history::SafariHistory};
+use browser_safari::{
+    bookmarks::SafariBookmarks, cloud_tabs::SafariCloudTabs, cookies::SafariCookies,
+    downloads::SafariDownloads, downloads_plist::DownloadsPlist, favicons::SafariFavicons,
+    history::SafariHistory, hsts::SafariHsts, quarantine::SafariQuarantine,
+    reading_list::SafariReadingList, recently_closed::SafariRecentlyClosed,
+    safari_tabs::SafariTabs, session::SafariSession, top_sites::SafariTopSites,
+};
 
 #[test]
 #[ignore = "Grabs live users history data"]
@@ -39,3 +45,121 @@ fn test_safari_bad_plist() {
     test_location.push("tests/test_data/Bad.plist");
     let _ = SafariDownloads::get_downloads(&test_location.display().to_string()).unwrap();
 }
+
+#[test]
+fn test_safari_downloads_plist() {
+    let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    test_location.push("tests/test_data/Downloads.plist");
+    let results = DownloadsPlist::parse_safari_plist(&test_location.display().to_string()).unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].bookmark_blob.len(), 720);
+}
+
+#[test]
+#[should_panic(expected = "Plist")]
+fn test_safari_downloads_plist_bad_file() {
+    let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    test_location.push("tests/test_data/badfile.txt");
+    let _ = DownloadsPlist::parse_safari_plist(&test_location.display().to_string()).unwrap();
+}
+
+#[test]
+#[ignore = "Grabs live users quarantine data"]
+fn system_safari_quarantine_test() {
+    let results = SafariQuarantine::get_users_quarantine().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[should_panic(expected = "BadSQL")]
+fn test_safari_bad_quarantine_db() {
+    let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    test_location.push("tests/test_data/BadHistory.db");
+    let _ = SafariQuarantine::get_quarantine(&test_location.display().to_string()).unwrap();
+}
+
+#[test]
+#[ignore = "Grabs live users bookmarks data"]
+fn system_safari_bookmarks_test() {
+    let results = SafariBookmarks::get_users_bookmarks().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[should_panic(expected = "Plist")]
+fn test_safari_bad_bookmarks() {
+    let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    test_location.push("tests/test_data/Bad.plist");
+    let _ = SafariBookmarks::get_bookmarks(&test_location.display().to_string()).unwrap();
+}
+
+#[test]
+#[ignore = "Grabs live users reading list data"]
+fn system_safari_reading_list_test() {
+    let results = SafariReadingList::get_users_reading_list().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[ignore = "Grabs live users top sites data"]
+fn system_safari_top_sites_test() {
+    let results = SafariTopSites::get_users_top_sites().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[ignore = "Grabs live users last session data"]
+fn system_safari_last_session_test() {
+    let results = SafariSession::get_users_last_session().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[ignore = "Grabs live users recently closed tabs data"]
+fn system_safari_recently_closed_test() {
+    let results = SafariRecentlyClosed::get_users_recently_closed().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[ignore = "Grabs live users tabs data"]
+fn system_safari_tabs_test() {
+    let results = SafariTabs::get_users_tabs().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[ignore = "Grabs live users cloud tabs data"]
+fn system_safari_cloud_tabs_test() {
+    let results = SafariCloudTabs::get_users_cloud_tabs().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[ignore = "Grabs live users cookies data"]
+fn system_safari_cookies_test() {
+    let results = SafariCookies::get_users_cookies().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[should_panic(expected = "Cookies")]
+fn test_safari_bad_cookies() {
+    let mut test_location = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    test_location.push("tests/test_data/badfile.txt");
+    let _ = SafariCookies::get_cookies(&test_location.display().to_string()).unwrap();
+}
+
+#[test]
+#[ignore = "Grabs live users HSTS data"]
+fn system_safari_hsts_test() {
+    let results = SafariHsts::get_users_hsts().unwrap();
+    assert!(!results.is_empty())
+}
+
+#[test]
+#[ignore = "Grabs live users favicons data"]
+fn system_safari_favicons_test() {
+    let results = SafariFavicons::get_users_favicons(false).unwrap();
+    assert!(!results.is_empty())
+}